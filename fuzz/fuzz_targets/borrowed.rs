@@ -0,0 +1,27 @@
+//! Decodes the input as every borrowed message type, which has to agree
+//! with decoding it as the owned one.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use robots::borrowed;
+use robots::serialize::deserializer::from_bytes;
+use robots::{ClientMessage, DisplayMessage, ServerMessage};
+
+macro_rules! agree {
+    ($data:expr, $borrowed:ty, $owned:ty) => {
+        let borrowed = from_bytes::<$borrowed>($data).ok();
+        let owned = from_bytes::<$owned>($data).ok();
+        assert_eq!(
+            borrowed.map(|message| <$owned>::try_from(message).expect("strings fit")),
+            owned
+        );
+    };
+}
+
+fuzz_target!(|data: &[u8]| {
+    agree!(data, borrowed::ClientMessage, ClientMessage);
+    agree!(data, borrowed::ServerMessage, ServerMessage);
+    agree!(data, borrowed::DisplayMessage, DisplayMessage);
+});
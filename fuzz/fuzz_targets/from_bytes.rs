@@ -0,0 +1,28 @@
+//! Decodes the input as every owned message type. Whatever decodes has to encode
+//! back into an equal message.
+
+#![no_main]
+
+use std::fmt::Debug;
+
+use libfuzzer_sys::fuzz_target;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use robots::serialize::deserializer::from_bytes;
+use robots::serialize::serializer::to_bytes;
+use robots::{ClientMessage, DisplayMessage, InputMessage, ServerMessage};
+
+fn round_trip<T: DeserializeOwned + Serialize + PartialEq + Debug>(data: &[u8]) {
+    if let Ok(message) = from_bytes::<T>(data) {
+        let decoded = from_bytes::<T>(&to_bytes(&message)).expect("encoded message decodes");
+        assert_eq!(decoded, message);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    round_trip::<ClientMessage>(data);
+    round_trip::<ServerMessage>(data);
+    round_trip::<DisplayMessage>(data);
+    round_trip::<InputMessage>(data);
+});
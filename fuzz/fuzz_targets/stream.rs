@@ -0,0 +1,16 @@
+//! Reads messages from the input as from a TCP stream, until the first error.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use robots::serialize::deserializer::Deserializer;
+use robots::{ClientMessage, ServerMessage};
+
+fuzz_target!(|data: &[u8]| {
+    let mut deserializer = Deserializer::new(data);
+    while deserializer.deserialize::<ClientMessage>().is_ok() {}
+
+    let mut deserializer = Deserializer::new(data);
+    while deserializer.deserialize::<ServerMessage>().is_ok() {}
+});
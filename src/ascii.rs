@@ -0,0 +1,315 @@
+//! The ASCII board notation used by the README to illustrate explosions:
+//!
+//! ```text
+//! .@2..
+//! ..1..
+//! @@A.@
+//! ```
+//!
+//! `.` is an empty field, `@` a block, `A`-`Z` bombs, `0`-`9` robots and `x` an explosion.
+//! The first line is the top row, since `(0, 0)` is the lower-left corner (README 2.5).
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::game::explosion;
+use crate::{Board, Position};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AsciiError {
+    #[error("Board is empty")]
+    Empty,
+    #[error("Board is too large")]
+    TooLarge,
+    #[error("Row {row} has length {actual}, expected {expected}")]
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("Unknown character {0:?}")]
+    UnknownCharacter(char),
+    #[error("Bomb {0} appears more than once")]
+    DuplicateBomb(char),
+    #[error("Robot {0} appears more than once")]
+    DuplicateRobot(char),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsciiBoard {
+    pub board: Board,
+    pub blocks: HashSet<Position>,
+    pub bombs: BTreeMap<char, Position>,
+    pub robots: BTreeMap<char, Position>,
+    pub explosions: HashSet<Position>,
+}
+
+/// Result of all bombs on a board exploding at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explosion {
+    /// Every field hit by any of the bombs.
+    pub hit: BTreeSet<Position>,
+    pub blocks_destroyed: BTreeSet<Position>,
+    pub robots_destroyed: BTreeSet<char>,
+}
+
+impl AsciiBoard {
+    pub fn new(board: Board) -> AsciiBoard {
+        AsciiBoard {
+            board,
+            blocks: HashSet::new(),
+            bombs: BTreeMap::new(),
+            robots: BTreeMap::new(),
+            explosions: HashSet::new(),
+        }
+    }
+
+    /// Explodes all bombs at once. Explosions already marked on the board are ignored.
+    pub fn explode(&self, radius: u16) -> Explosion {
+        let hit = self
+            .bombs
+            .values()
+            .flat_map(|&bomb| explosion(bomb, radius, &self.board, &self.blocks))
+            .collect::<BTreeSet<_>>();
+        let blocks_destroyed = hit
+            .iter()
+            .copied()
+            .filter(|position| self.blocks.contains(position))
+            .collect();
+        let robots_destroyed = self
+            .robots
+            .iter()
+            .filter(|(_, position)| hit.contains(position))
+            .map(|(&robot, _)| robot)
+            .collect();
+        Explosion {
+            hit,
+            blocks_destroyed,
+            robots_destroyed,
+        }
+    }
+
+    /// The board with the explosion marked on it, like the second board of each README example.
+    pub fn with_explosion(&self, explosion: &Explosion) -> AsciiBoard {
+        AsciiBoard {
+            explosions: explosion.hit.iter().copied().collect(),
+            ..self.clone()
+        }
+    }
+
+    /// The board after the explosion: without the bombs and the destroyed blocks and robots.
+    pub fn after_explosion(&self, explosion: &Explosion) -> AsciiBoard {
+        let mut after = self.clone();
+        after.bombs.clear();
+        after.explosions.clear();
+        after
+            .blocks
+            .retain(|position| !explosion.blocks_destroyed.contains(position));
+        after
+            .robots
+            .retain(|robot, _| !explosion.robots_destroyed.contains(robot));
+        after
+    }
+
+    /// The character shown at the given field. When several things are on one field,
+    /// explosions take precedence over robots, robots over bombs and bombs over blocks.
+    pub fn field(&self, position: Position) -> char {
+        let find = |things: &BTreeMap<char, Position>| {
+            things
+                .iter()
+                .find(|(_, &thing)| thing == position)
+                .map(|(&c, _)| c)
+        };
+        if self.explosions.contains(&position) {
+            'x'
+        } else if let Some(robot) = find(&self.robots) {
+            robot
+        } else if let Some(bomb) = find(&self.bombs) {
+            bomb
+        } else if self.blocks.contains(&position) {
+            '@'
+        } else {
+            '.'
+        }
+    }
+}
+
+impl FromStr for AsciiBoard {
+    type Err = AsciiError;
+
+    /// Blank lines and surrounding whitespace are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| line.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let size_x = rows.first().map(Vec::len).ok_or(AsciiError::Empty)?;
+        let size_y = rows.len();
+        let board = Board::new(
+            size_x.try_into().map_err(|_| AsciiError::TooLarge)?,
+            size_y.try_into().map_err(|_| AsciiError::TooLarge)?,
+        )
+        .ok_or(AsciiError::Empty)?;
+
+        let mut ascii = AsciiBoard::new(board);
+        for (row, line) in rows.iter().enumerate() {
+            if line.len() != size_x {
+                return Err(AsciiError::RaggedRow {
+                    row,
+                    expected: size_x,
+                    actual: line.len(),
+                });
+            }
+            let y = (size_y - 1 - row) as u16;
+            for (x, &c) in line.iter().enumerate() {
+                let position = Position(x as u16, y);
+                match c {
+                    '.' => {}
+                    '@' => {
+                        ascii.blocks.insert(position);
+                    }
+                    'x' => {
+                        ascii.explosions.insert(position);
+                    }
+                    'A'..='Z' => {
+                        if ascii.bombs.insert(c, position).is_some() {
+                            return Err(AsciiError::DuplicateBomb(c));
+                        }
+                    }
+                    '0'..='9' => {
+                        if ascii.robots.insert(c, position).is_some() {
+                            return Err(AsciiError::DuplicateRobot(c));
+                        }
+                    }
+                    _ => return Err(AsciiError::UnknownCharacter(c)),
+                }
+            }
+        }
+        Ok(ascii)
+    }
+}
+
+impl fmt::Display for AsciiBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in (0..self.board.size_y()).rev() {
+            for x in 0..self.board.size_x() {
+                write!(f, "{}", self.field(Position(x, y)))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn board(s: &str) -> AsciiBoard {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_print() {
+        let input = ".@2..\n..1..\n@@A.@\n..@..\n.....\n";
+        let ascii = board(input);
+        assert_eq!(ascii.board, Board::new(5, 5).unwrap());
+        assert_eq!(ascii.bombs, BTreeMap::from([('A', Position(2, 2))]));
+        assert_eq!(
+            ascii.robots,
+            BTreeMap::from([('1', Position(2, 3)), ('2', Position(2, 4))])
+        );
+        assert!(ascii.blocks.contains(&Position(1, 4)));
+        assert!(ascii.blocks.contains(&Position(2, 1)));
+        assert_eq!(ascii.to_string(), input);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!("".parse::<AsciiBoard>(), Err(AsciiError::Empty));
+        assert_eq!(
+            "...\n..".parse::<AsciiBoard>(),
+            Err(AsciiError::RaggedRow {
+                row: 1,
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            ".#.".parse::<AsciiBoard>(),
+            Err(AsciiError::UnknownCharacter('#'))
+        );
+        assert_eq!(
+            "A.A".parse::<AsciiBoard>(),
+            Err(AsciiError::DuplicateBomb('A'))
+        );
+    }
+
+    // README 2.8, first example.
+    #[test]
+    fn readme_single_bomb() {
+        let before = board(
+            "
+            .@2..
+            ..1..
+            @@A.@
+            ..@..
+            .....
+            ",
+        );
+        let explosion = before.explode(2);
+        assert_eq!(
+            before.with_explosion(&explosion).to_string(),
+            ".@x..\n..x..\n@xxxx\n..x..\n.....\n"
+        );
+        // "A zatem zniszczone zostaną 3 bloki i oba roboty."
+        assert_eq!(explosion.blocks_destroyed.len(), 3);
+        assert_eq!(explosion.robots_destroyed, BTreeSet::from(['1', '2']));
+    }
+
+    // README 2.8, second example.
+    #[test]
+    fn readme_simultaneous_bombs() {
+        let before = board(
+            "
+            @@@@@
+            @@AB@
+            .@@@@
+            ",
+        );
+        let explosion = before.explode(2);
+        assert_eq!(
+            before.with_explosion(&explosion).to_string(),
+            "@@xx@\n@xxxx\n.@xx@\n"
+        );
+        assert_eq!(
+            before.after_explosion(&explosion).to_string(),
+            "@@..@\n@....\n.@..@\n"
+        );
+        // "Widać że blok o współrzędnych (0, 1) nie został zniszczony."
+        assert!(!explosion.blocks_destroyed.contains(&Position(0, 1)));
+    }
+
+    #[test]
+    fn robots_on_destroyed_block_die() {
+        // The notation cannot show a robot on a block, so put the block under it afterwards.
+        let mut before = board("A.1");
+        before.blocks.insert(Position(2, 0));
+        let explosion = before.explode(5);
+        assert_eq!(explosion.blocks_destroyed, BTreeSet::from([Position(2, 0)]));
+        assert_eq!(explosion.robots_destroyed, BTreeSet::from(['1']));
+    }
+
+    #[test]
+    fn robots_behind_blocks_survive() {
+        let before = board("A@1");
+        let explosion = before.explode(5);
+        assert!(explosion.robots_destroyed.is_empty());
+        assert_eq!(before.after_explosion(&explosion).to_string(), "..1\n");
+    }
+}
@@ -0,0 +1,431 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::process::exit;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+
+use robots::client::ClientState;
+use robots::game::GameParameters;
+use robots::harness::{self, Connection, Datagrams, HarnessError, Outcome, Process, Scenario};
+use robots::simulation::{simulate, Script};
+use robots::{
+    ClientMessage, Direction, DisplayMessage, InputMessage, Player, PlayerName, ServerMessage,
+};
+
+/// Runs scenarios against a client executable, with a fake server and a fake GUI
+/// on localhost in place of the real ones.
+#[derive(Parser, Debug, Clone)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Path to the `robots-client` executable.
+    #[clap(required_unless_present = "list")]
+    client: Option<PathBuf>,
+
+    /// Scenarios to run. All of them are run if none is given.
+    #[clap(short = 'r', long = "run")]
+    scenarios: Vec<String>,
+
+    /// List the scenarios and exit.
+    #[clap(long)]
+    list: bool,
+
+    /// How long to wait for a single reaction of the client, in milliseconds.
+    #[clap(short, long, default_value_t = 3000)]
+    timeout: u64,
+
+    /// Show the output of the client.
+    #[clap(short, long)]
+    verbose: bool,
+}
+
+/// How often [`Session::input_until`] resends the input.
+const RESEND_INTERVAL: Duration = Duration::from_millis(200);
+
+const SCENARIOS: &[Scenario<Context>] = &[
+    Scenario {
+        name: "lobby-input-joins",
+        description: "an input from the GUI in the lobby makes the client send Join",
+        run: lobby_input_joins,
+    },
+    Scenario {
+        name: "invalid-input-ignored",
+        description: "a datagram from the GUI which cannot be parsed is ignored",
+        run: invalid_input_ignored,
+    },
+    Scenario {
+        name: "accepted-player-lobby",
+        description: "AcceptedPlayer makes the client send Lobby with the player",
+        run: accepted_player_lobby,
+    },
+    Scenario {
+        name: "turn-game-display",
+        description: "every Turn makes the client send Game with the right state and scores",
+        run: turn_game_display,
+    },
+    Scenario {
+        name: "input-forwarded-in-game",
+        description: "inputs from the GUI during a game are forwarded to the server",
+        run: input_forwarded_in_game,
+    },
+    Scenario {
+        name: "server-disconnect",
+        description: "the client exits with code 1 when the server disconnects",
+        run: server_disconnect,
+    },
+    Scenario {
+        name: "localhost",
+        description: "addresses given with the name localhost are resolved",
+        run: localhost,
+    },
+    Scenario {
+        name: "ipv6",
+        description: "the server and the GUI are reachable over IPv6",
+        run: ipv6,
+    },
+];
+
+struct Context {
+    args: Args,
+    client: PathBuf,
+    timeout: Duration,
+}
+
+/// A client under test, connected to the fake server and talking to the fake GUI.
+struct Session {
+    process: Process,
+    server: Connection,
+    gui: Datagrams,
+    /// Where the client listens for the GUI.
+    input_address: SocketAddr,
+    name: PlayerName,
+    state: ClientState,
+    /// The last message the server got from [`Session::input_until`].
+    forwarded: Option<ClientMessage>,
+    timeout: Duration,
+}
+
+fn parameters() -> GameParameters {
+    GameParameters {
+        server_name: "fake server".parse().unwrap(),
+        players_count: 2,
+        size_x: 7,
+        size_y: 6,
+        game_length: 8,
+        explosion_radius: 2,
+        bomb_timer: 2,
+        initial_blocks: 8,
+    }
+}
+
+fn other_player() -> Player {
+    Player {
+        name: "somebody else".parse().unwrap(),
+        address: "127.0.0.1:40000".parse().unwrap(),
+    }
+}
+
+/// Listens on all interfaces if possible, so that both IPv4 and IPv6 clients can connect.
+fn bind_server() -> std::io::Result<TcpListener> {
+    TcpListener::bind((Ipv6Addr::UNSPECIFIED, 0)).or_else(|_| TcpListener::bind("127.0.0.1:0"))
+}
+
+fn bind_gui() -> std::io::Result<Datagrams> {
+    Datagrams::bind((Ipv6Addr::UNSPECIFIED, 0)).or_else(|_| Datagrams::bind("127.0.0.1:0"))
+}
+
+impl Session {
+    /// Starts the client with the given host names for the server and the GUI,
+    /// waits for it to connect and sends `Hello`.
+    fn start(context: &Context, host: &str) -> Result<Session, String> {
+        let listener = bind_server().map_err(|e| format!("Cannot listen: {}", e))?;
+        let gui = bind_gui().map_err(|e| format!("Cannot bind the GUI socket: {}", e))?;
+        let server_port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        let gui_port = gui.local_addr().map_err(|e| e.to_string())?.port();
+        let input_port = harness::free_udp_port().map_err(|e| format!("No free port: {}", e))?;
+        let name = PlayerName::new("tested client").unwrap();
+
+        let args = [
+            ("-d", format!("{}:{}", host, gui_port)),
+            ("-n", name.to_string()),
+            ("-p", input_port.to_string()),
+            ("-s", format!("{}:{}", host, server_port)),
+        ]
+        .into_iter()
+        .flat_map(|(flag, value)| [flag.to_string(), value])
+        .collect::<Vec<_>>();
+        let mut process = Process::spawn(&context.client, &args, context.args.verbose)
+            .map_err(|e| format!("Cannot start {}: {}", context.client.display(), e))?;
+
+        let stream = accept(&listener, &mut process, context.timeout)?;
+        let input_ip = match host
+            .trim_matches(|c| c == '[' || c == ']')
+            .parse::<IpAddr>()
+        {
+            Ok(ip) => ip,
+            Err(_) => Ipv4Addr::LOCALHOST.into(),
+        };
+        let mut session = Session {
+            process,
+            server: Connection::new(stream).map_err(|e| e.to_string())?,
+            gui,
+            input_address: SocketAddr::new(input_ip, input_port),
+            name,
+            state: ClientState::new(),
+            forwarded: None,
+            timeout: context.timeout,
+        };
+        session.send(&parameters().hello())?;
+        Ok(session)
+    }
+
+    /// Sends a message from the fake server and returns what the GUI should get in reaction.
+    fn send(&mut self, message: &ServerMessage) -> Result<Option<DisplayMessage>, String> {
+        self.server
+            .send(message)
+            .map_err(|e| format!("Cannot send {:?}: {}", message, e))?;
+        Ok(self.state.apply(message))
+    }
+
+    /// Sends a message from the fake server and waits for the display it should cause.
+    fn send_and_expect(&mut self, message: &ServerMessage) -> Outcome {
+        match self.send(message)? {
+            Some(display) => self
+                .expect_display(&display)
+                .map_err(|e| format!("After {:?}: {}", message, e)),
+            None => Ok(()),
+        }
+    }
+
+    fn send_input(&self, input: &InputMessage) -> Outcome {
+        self.gui
+            .send_to(input, self.input_address)
+            .map_err(|e| format!("Cannot send {:?}: {}", input, e))
+    }
+
+    /// Waits until the GUI gets the expected message. Other messages, e.g. repeated
+    /// displays of an earlier state, are skipped.
+    fn expect_display(&self, expected: &DisplayMessage) -> Outcome {
+        let deadline = Instant::now() + self.timeout;
+        let mut last = None;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(match last {
+                    Some(last) => format!("Expected {:?}, last got {:?}", expected, last),
+                    None => format!("Expected {:?}, got nothing", expected),
+                });
+            }
+            match self.gui.receive::<DisplayMessage>(left) {
+                Ok((display, _)) if &display == expected => return Ok(()),
+                Ok((display, _)) => last = Some(display),
+                Err(HarnessError::Timeout) => continue,
+                Err(e) => return Err(format!("Invalid message for the GUI: {}", e)),
+            }
+        }
+    }
+
+    /// Sends the input until the server gets the expected message. The client may
+    /// still be starting up when the first datagrams arrive, so they can get lost,
+    /// and the resent ones can make it forward copies of the previous message.
+    fn input_until(&mut self, input: &InputMessage, expected: &ClientMessage) -> Outcome {
+        let deadline = Instant::now() + self.timeout;
+        while Instant::now() < deadline {
+            self.send_input(input)?;
+            match self.server.readable(RESEND_INTERVAL) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => return Err(format!("Waiting for {:?}: {}", expected, e)),
+            }
+            // Only a whole message may time out, or the stream would be left out of sync.
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.server.receive::<ClientMessage>(left) {
+                Ok(message) if &message == expected => {
+                    self.forwarded = Some(message);
+                    return Ok(());
+                }
+                Ok(message) if Some(&message) == self.forwarded.as_ref() => continue,
+                Ok(message) => {
+                    return Err(format!(
+                        "After {:?} expected {:?}, got {:?}",
+                        input, expected, message
+                    ))
+                }
+                Err(e) => return Err(format!("Waiting for {:?}: {}", expected, e)),
+            }
+        }
+        Err(format!(
+            "After {:?} expected {:?}, got nothing",
+            input, expected
+        ))
+    }
+
+    fn join(&mut self) -> Outcome {
+        let join = ClientMessage::Join {
+            name: self.name.clone(),
+        };
+        self.input_until(&InputMessage::PlaceBomb, &join)
+    }
+
+    fn check_alive(&mut self) -> Outcome {
+        match self.process.exit_status() {
+            Ok(None) => Ok(()),
+            Ok(Some(status)) => Err(format!("Client exited with {}", status)),
+            Err(e) => Err(format!("Cannot check the client: {}", e)),
+        }
+    }
+}
+
+fn accept(
+    listener: &TcpListener,
+    process: &mut Process,
+    timeout: Duration,
+) -> Result<std::net::TcpStream, String> {
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+                return Ok(stream);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                if let Ok(Some(status)) = process.exit_status() {
+                    return Err(format!("Client exited with {} before connecting", status));
+                }
+                if Instant::now() >= deadline {
+                    return Err("The client did not connect to the server".to_string());
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(e) => return Err(format!("Cannot accept: {}", e)),
+        }
+    }
+}
+
+fn lobby_input_joins(context: &Context) -> Outcome {
+    let mut session = Session::start(context, "127.0.0.1")?;
+    session.join()?;
+    session.check_alive()
+}
+
+fn invalid_input_ignored(context: &Context) -> Outcome {
+    let mut session = Session::start(context, "127.0.0.1")?;
+    // An invalid tag, a truncated move and trailing data.
+    for garbage in [&[7u8][..], &[2], &[0, 0]] {
+        session
+            .gui
+            .send_bytes_to(garbage, session.input_address)
+            .map_err(|e| e.to_string())?;
+    }
+    session.join()?;
+    session.check_alive()
+}
+
+fn accepted_player_lobby(context: &Context) -> Outcome {
+    let mut session = Session::start(context, "127.0.0.1")?;
+    session.send_and_expect(&ServerMessage::AcceptedPlayer {
+        id: robots::PlayerId(0),
+        player: other_player(),
+    })?;
+    session.check_alive()
+}
+
+fn turn_game_display(context: &Context) -> Outcome {
+    let mut session = Session::start(context, "127.0.0.1")?;
+    let players = [
+        other_player(),
+        Player {
+            name: session.name.clone(),
+            address: "127.0.0.1:40001".parse().unwrap(),
+        },
+    ];
+    // Bombs, blocks and deaths, so that explosions and scores are shown.
+    let script = Script::parse(
+        "bomb bomb\nup down\nleft right\nbomb block\nbomb bomb\nright up\n- left",
+        2,
+    )
+    .unwrap();
+    let messages = simulate(&parameters(), 2022, &players, &script).map_err(|e| e.to_string())?;
+    // `Hello` has already been sent.
+    for message in &messages[1..] {
+        session.send_and_expect(message)?;
+    }
+    session.check_alive()
+}
+
+fn input_forwarded_in_game(context: &Context) -> Outcome {
+    let mut session = Session::start(context, "127.0.0.1")?;
+    session.join()?;
+    let players = [
+        Player {
+            name: session.name.clone(),
+            address: "127.0.0.1:40001".parse().unwrap(),
+        },
+        other_player(),
+    ];
+    let messages =
+        simulate(&parameters(), 1, &players, &Script::default()).map_err(|e| e.to_string())?;
+    // Everything up to and including turn 0.
+    for message in &messages[1..5] {
+        session.send_and_expect(message)?;
+    }
+    let inputs = [
+        InputMessage::Move {
+            direction: Direction::Left,
+        },
+        InputMessage::PlaceBlock,
+        InputMessage::PlaceBomb,
+    ];
+    for input in &inputs {
+        session.input_until(input, &input.clone().into())?;
+    }
+    session.check_alive()
+}
+
+fn server_disconnect(context: &Context) -> Outcome {
+    let Session {
+        mut process,
+        server,
+        ..
+    } = Session::start(context, "127.0.0.1")?;
+    drop(server);
+    match process.wait_timeout(context.timeout) {
+        Ok(Some(status)) if status.code() == Some(1) => Ok(()),
+        Ok(Some(status)) => Err(format!("Client exited with {}, expected code 1", status)),
+        Ok(None) => Err("Client is still running".to_string()),
+        Err(e) => Err(format!("Cannot check the client: {}", e)),
+    }
+}
+
+fn localhost(context: &Context) -> Outcome {
+    let mut session = Session::start(context, "localhost")?;
+    session.send_and_expect(&ServerMessage::AcceptedPlayer {
+        id: robots::PlayerId(0),
+        player: other_player(),
+    })?;
+    session.join()?;
+    session.check_alive()
+}
+
+fn ipv6(context: &Context) -> Outcome {
+    let mut session = Session::start(context, "[::1]")?;
+    session.send_and_expect(&ServerMessage::AcceptedPlayer {
+        id: robots::PlayerId(0),
+        player: other_player(),
+    })?;
+    session.join()?;
+    session.check_alive()
+}
+
+fn main() {
+    let args = Args::parse();
+    let (list, names) = (args.list, args.scenarios.clone());
+    let code = harness::run_scenarios(SCENARIOS, list, &names, || Context {
+        client: args.client.clone().expect("required unless listing"),
+        timeout: Duration::from_millis(args.timeout),
+        args,
+    });
+    exit(code)
+}
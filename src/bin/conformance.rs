@@ -0,0 +1,531 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
+
+use clap::Parser;
+
+use robots::game::GameParameters;
+use robots::harness::{self, Connection, Outcome, Process, Scenario};
+use robots::simulation::Script;
+use robots::trace::{check_trace, TraceRecord};
+use robots::{ClientMessage, Event, PlayerAddress, PlayerId, PlayerName, ServerMessage};
+
+/// Runs the scenarios from README 6.2 against a server executable, over localhost only.
+#[derive(Parser, Debug, Clone)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Path to the `robots-server` executable.
+    #[clap(required_unless_present = "list")]
+    server: Option<PathBuf>,
+
+    /// Scenarios to run. All of them are run if none is given.
+    #[clap(short = 'r', long = "run")]
+    scenarios: Vec<String>,
+
+    /// List the scenarios and exit.
+    #[clap(long)]
+    list: bool,
+
+    /// Turn duration passed to the server, in milliseconds.
+    #[clap(short = 'd', long, default_value_t = 100)]
+    turn_duration: u64,
+
+    /// How long to wait for a single message, in milliseconds.
+    #[clap(short, long, default_value_t = 3000)]
+    timeout: u64,
+
+    /// Directory to save the traces of failed game scenarios to, for `oracle` and `tracediff`.
+    #[clap(long)]
+    save_traces: Option<PathBuf>,
+
+    /// Show the output of the server.
+    #[clap(short, long)]
+    verbose: bool,
+}
+
+const SCENARIOS: &[Scenario<Context>] = &[
+    Scenario {
+        name: "hello-ipv4",
+        description: "a client connecting over IPv4 receives Hello with the server parameters",
+        run: hello_ipv4,
+    },
+    Scenario {
+        name: "hello-ipv6",
+        description: "a client connecting over IPv6 receives Hello with the server parameters",
+        run: hello_ipv6,
+    },
+    Scenario {
+        name: "localhost",
+        description: "the server is reachable through the name localhost",
+        run: localhost,
+    },
+    Scenario {
+        name: "join-broadcast",
+        description: "Join of a player is announced to every client with AcceptedPlayer",
+        run: join_broadcast,
+    },
+    Scenario {
+        name: "board-from-seed",
+        description: "robots and initial blocks are placed with the generator from README 2.4",
+        run: board_from_seed,
+    },
+    Scenario {
+        name: "single-player-game",
+        description: "a short game of a single player follows the rules",
+        run: single_player_game,
+    },
+    Scenario {
+        name: "explosion",
+        description: "explosions destroy the right blocks and robots",
+        run: explosion,
+    },
+    Scenario {
+        name: "non-ascii-names",
+        description: "player names outside ASCII are passed on byte for byte",
+        run: non_ascii_names,
+    },
+    Scenario {
+        name: "late-observer",
+        description: "a client connecting during a game receives GameStarted and all turns so far",
+        run: late_observer,
+    },
+    Scenario {
+        name: "multi-game",
+        description: "several games with several players, one after another, follow the rules",
+        run: multi_game,
+    },
+];
+
+struct Context {
+    args: Args,
+    server: PathBuf,
+    timeout: Duration,
+}
+
+/// A server started for a single scenario.
+struct Server {
+    process: Process,
+    port: u16,
+    parameters: GameParameters,
+    seed: u32,
+}
+
+impl Server {
+    fn start(context: &Context, parameters: GameParameters, seed: u32) -> Result<Server, String> {
+        let port = harness::free_tcp_port().map_err(|e| format!("No free port: {}", e))?;
+        let args = [
+            ("-b", parameters.bomb_timer.to_string()),
+            ("-c", parameters.players_count.to_string()),
+            ("-d", context.args.turn_duration.to_string()),
+            ("-e", parameters.explosion_radius.to_string()),
+            ("-k", parameters.initial_blocks.to_string()),
+            ("-l", parameters.game_length.to_string()),
+            ("-n", parameters.server_name.to_string()),
+            ("-p", port.to_string()),
+            ("-s", seed.to_string()),
+            ("-x", parameters.size_x.to_string()),
+            ("-y", parameters.size_y.to_string()),
+        ]
+        .into_iter()
+        .flat_map(|(flag, value)| [flag.to_string(), value])
+        .collect::<Vec<_>>();
+        let process = Process::spawn(&context.server, &args, context.args.verbose)
+            .map_err(|e| format!("Cannot start {}: {}", context.server.display(), e))?;
+        Ok(Server {
+            process,
+            port,
+            parameters,
+            seed,
+        })
+    }
+
+    fn address(&self, ip: IpAddr) -> SocketAddr {
+        SocketAddr::new(ip, self.port)
+    }
+
+    /// Connects a new client and checks the `Hello` it receives first.
+    fn connect_to(&self, context: &Context, address: SocketAddr) -> Result<Client, String> {
+        let connection = harness::connect(address, context.timeout)
+            .map_err(|e| format!("Cannot connect to {}: {}", address, e))?;
+        let mut client = Client {
+            connection,
+            timeout: context.timeout,
+        };
+        let hello = client.receive("Hello")?;
+        if hello != self.parameters.hello() {
+            return Err(format!(
+                "Expected {:?}, got {:?}",
+                self.parameters.hello(),
+                hello
+            ));
+        }
+        Ok(client)
+    }
+
+    fn connect(&self, context: &Context) -> Result<Client, String> {
+        self.connect_to(context, self.address(Ipv4Addr::LOCALHOST.into()))
+    }
+
+    /// The server has to survive every scenario.
+    fn check_alive(&mut self) -> Outcome {
+        match self.process.exit_status() {
+            Ok(None) => Ok(()),
+            Ok(Some(status)) => Err(format!("Server exited with {}", status)),
+            Err(e) => Err(format!("Cannot check the server: {}", e)),
+        }
+    }
+}
+
+struct Client {
+    connection: Connection,
+    timeout: Duration,
+}
+
+impl Client {
+    fn send(&mut self, message: &ClientMessage) -> Outcome {
+        self.connection
+            .send(message)
+            .map_err(|e| format!("Cannot send {:?}: {}", message, e))
+    }
+
+    fn receive(&mut self, expected: &str) -> Result<ServerMessage, String> {
+        self.connection
+            .receive(self.timeout)
+            .map_err(|e| format!("Waiting for {}: {}", expected, e))
+    }
+
+    fn address(&self) -> SocketAddr {
+        self.connection
+            .local_addr()
+            .expect("connected socket has an address")
+    }
+}
+
+/// IPv4 clients of a dual-stack server show up as IPv4-mapped IPv6 addresses.
+fn same_address(reported: &PlayerAddress, actual: SocketAddr) -> bool {
+    let canonical = |address: SocketAddr| match address.ip() {
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => SocketAddr::new(ip.into(), address.port()),
+            None => address,
+        },
+        IpAddr::V4(_) => address,
+    };
+    reported.socket_addr().map(canonical) == Some(canonical(actual))
+}
+
+/// Receives the next message on every connection and checks that all of them got the same one.
+fn receive_all(clients: &mut [Client], expected: &str) -> Result<ServerMessage, String> {
+    let (first, rest) = clients.split_first_mut().expect("at least one client");
+    let message = first.receive(expected)?;
+    for (index, client) in rest.iter_mut().enumerate() {
+        let other = client.receive(expected)?;
+        if other != message {
+            return Err(format!(
+                "Client 0 received {:?}, but client {} received {:?}",
+                message,
+                index + 1,
+                other
+            ));
+        }
+    }
+    Ok(message)
+}
+
+/// Plays one game in which `clients[i]` joins as the i-th player, following the script.
+/// The first client's view, together with the moves, is appended to `trace`.
+fn play_game(
+    clients: &mut [Client],
+    names: &[PlayerName],
+    script: &Script,
+    game_length: u16,
+    trace: &mut Vec<TraceRecord>,
+) -> Outcome {
+    for (index, name) in names.iter().enumerate() {
+        clients[index].send(&ClientMessage::Join { name: name.clone() })?;
+        let message = receive_all(clients, "AcceptedPlayer")?;
+        match &message {
+            ServerMessage::AcceptedPlayer { player, .. } if &player.name == name => {}
+            other => {
+                return Err(format!(
+                    "Expected AcceptedPlayer for {}, got {:?}",
+                    name, other
+                ))
+            }
+        }
+        trace.push(TraceRecord::Server(message));
+    }
+
+    let message = receive_all(clients, "GameStarted")?;
+    if !matches!(message, ServerMessage::GameStarted { .. }) {
+        return Err(format!("Expected GameStarted, got {:?}", message));
+    }
+    trace.push(TraceRecord::Server(message));
+
+    let mut turn = 0;
+    loop {
+        let message = receive_all(clients, "Turn")?;
+        let ended = matches!(message, ServerMessage::GameEnded { .. });
+        trace.push(TraceRecord::Server(message));
+        if ended {
+            return Ok(());
+        }
+        turn += 1;
+        // Moves sent during the last turn are ignored (README 2.10), so none are sent then.
+        if turn < game_length {
+            for (id, message) in script.moves(turn) {
+                clients[id.0 as usize].send(&message)?;
+                trace.push(TraceRecord::Client { id, message });
+            }
+        }
+    }
+}
+
+/// Checks a whole session with the oracle, saving the trace if it does not follow the rules.
+fn check_session(context: &Context, name: &str, server: &Server, trace: &[TraceRecord]) -> Outcome {
+    match check_trace(trace, server.seed, server.parameters.initial_blocks) {
+        Ok(_) => Ok(()),
+        Err(divergence) => {
+            let mut message = divergence.to_string();
+            if let Some(directory) = &context.args.save_traces {
+                let path = directory.join(format!("{}.jsonl", name));
+                let lines = trace
+                    .iter()
+                    .map(|record| serde_json::to_string(record).expect("records serialize"))
+                    .collect::<Vec<_>>();
+                match std::fs::write(&path, lines.join("\n") + "\n") {
+                    Ok(()) => message += &format!(" (trace saved to {})", path.display()),
+                    Err(e) => message += &format!(" (cannot save trace: {})", e),
+                }
+            }
+            Err(message)
+        }
+    }
+}
+
+fn parameters(players_count: u8) -> GameParameters {
+    GameParameters {
+        server_name: "conformance".parse().unwrap(),
+        players_count,
+        size_x: 8,
+        size_y: 6,
+        game_length: 12,
+        explosion_radius: 2,
+        bomb_timer: 2,
+        initial_blocks: 10,
+    }
+}
+
+fn names(count: u8) -> Vec<PlayerName> {
+    (0..count)
+        .map(|id| format!("player{}", id).parse().unwrap())
+        .collect()
+}
+
+fn hello_ipv4(context: &Context) -> Outcome {
+    let mut server = Server::start(context, parameters(1), 1)?;
+    server.connect(context)?;
+    server.check_alive()
+}
+
+fn hello_ipv6(context: &Context) -> Outcome {
+    let mut server = Server::start(context, parameters(1), 1)?;
+    server.connect_to(context, server.address(Ipv6Addr::LOCALHOST.into()))?;
+    server.check_alive()
+}
+
+fn localhost(context: &Context) -> Outcome {
+    let mut server = Server::start(context, parameters(1), 1)?;
+    let address = format!("localhost:{}", server.port);
+    let connection = harness::connect(address.as_str(), context.timeout)
+        .map_err(|e| format!("Cannot connect to {}: {}", address, e))?;
+    let mut client = Client {
+        connection,
+        timeout: context.timeout,
+    };
+    client.receive("Hello")?;
+    server.check_alive()
+}
+
+fn join_broadcast(context: &Context) -> Outcome {
+    let mut server = Server::start(context, parameters(2), 1)?;
+    let mut clients = vec![server.connect(context)?, server.connect(context)?];
+    let name = PlayerName::new("joining").unwrap();
+    clients[1].send(&ClientMessage::Join { name: name.clone() })?;
+    let address = clients[1].address();
+    match receive_all(&mut clients, "AcceptedPlayer")? {
+        ServerMessage::AcceptedPlayer { id, player } => {
+            if id != PlayerId(0) {
+                return Err(format!("The first player got id {}", id.0));
+            }
+            if player.name != name {
+                return Err(format!("Expected name {:?}, got {:?}", name, player.name));
+            }
+            if !same_address(&player.address, address) {
+                return Err(format!(
+                    "Expected address {}, got {}",
+                    address, player.address
+                ));
+            }
+        }
+        other => return Err(format!("Expected AcceptedPlayer, got {:?}", other)),
+    }
+    server.check_alive()
+}
+
+fn board_from_seed(context: &Context) -> Outcome {
+    let parameters = GameParameters {
+        size_x: 13,
+        size_y: 7,
+        game_length: 2,
+        initial_blocks: 40,
+        ..parameters(3)
+    };
+    let mut server = Server::start(context, parameters, 2022)?;
+    let mut clients = (0..3)
+        .map(|_| server.connect(context))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut trace = vec![TraceRecord::Server(server.parameters.hello())];
+    play_game(&mut clients, &names(3), &Script::default(), 2, &mut trace)?;
+    check_session(context, "board-from-seed", &server, &trace)?;
+    server.check_alive()
+}
+
+fn single_player_game(context: &Context) -> Outcome {
+    let parameters = GameParameters {
+        game_length: 8,
+        ..parameters(1)
+    };
+    let mut server = Server::start(context, parameters, 77)?;
+    let mut clients = vec![server.connect(context)?];
+    let script = Script::parse("up\nright\nright\ndown\nblock\nleft\nup", 1).unwrap();
+    let mut trace = vec![TraceRecord::Server(server.parameters.hello())];
+    play_game(&mut clients, &names(1), &script, 8, &mut trace)?;
+    check_session(context, "single-player-game", &server, &trace)?;
+    server.check_alive()
+}
+
+fn explosion(context: &Context) -> Outcome {
+    let parameters = GameParameters {
+        initial_blocks: 25,
+        ..parameters(2)
+    };
+    let mut server = Server::start(context, parameters, 31337)?;
+    let mut clients = vec![server.connect(context)?, server.connect(context)?];
+    // Bombs placed in turn 1 explode in turn 3, and the one placed on a block in turn 6.
+    let script = Script::parse(
+        "bomb bomb\nup left\nright down\nblock block\nbomb -\n- up\nleft bomb\n",
+        2,
+    )
+    .unwrap();
+    let mut trace = vec![TraceRecord::Server(server.parameters.hello())];
+    play_game(&mut clients, &names(2), &script, 12, &mut trace)?;
+    let exploded = trace.iter().any(|record| {
+        matches!(record, TraceRecord::Server(ServerMessage::Turn { events, .. })
+            if events.iter().any(|event| matches!(event, Event::BombExploded { .. })))
+    });
+    if !exploded {
+        return Err("No bomb exploded".to_string());
+    }
+    check_session(context, "explosion", &server, &trace)?;
+    server.check_alive()
+}
+
+fn non_ascii_names(context: &Context) -> Outcome {
+    let parameters = GameParameters {
+        server_name: "Serwer żółwi 🐢".parse().unwrap(),
+        game_length: 2,
+        ..parameters(2)
+    };
+    let mut server = Server::start(context, parameters, 5)?;
+    let mut clients = vec![server.connect(context)?, server.connect(context)?];
+    let names = vec![
+        PlayerName::new("Zażółć gęślą jaźń").unwrap(),
+        PlayerName::new("ロボット 🤖").unwrap(),
+    ];
+    let mut trace = vec![TraceRecord::Server(server.parameters.hello())];
+    play_game(&mut clients, &names, &Script::default(), 2, &mut trace)?;
+    let started = trace.iter().find_map(|record| match record {
+        TraceRecord::Server(ServerMessage::GameStarted { players }) => Some(players),
+        _ => None,
+    });
+    let reported = started
+        .into_iter()
+        .flat_map(|players| players.values().map(|player| player.name.clone()))
+        .collect::<Vec<_>>();
+    if reported != names {
+        return Err(format!("Expected names {:?}, got {:?}", names, reported));
+    }
+    server.check_alive()
+}
+
+fn late_observer(context: &Context) -> Outcome {
+    let parameters = GameParameters {
+        game_length: 20,
+        ..parameters(1)
+    };
+    let mut server = Server::start(context, parameters, 9)?;
+    let mut player = server.connect(context)?;
+    player.send(&ClientMessage::Join {
+        name: "early".parse().unwrap(),
+    })?;
+    let mut seen = Vec::new();
+    loop {
+        let message = player.receive("Turn 3")?;
+        let done = matches!(message, ServerMessage::Turn { turn: 3, .. });
+        if !matches!(message, ServerMessage::AcceptedPlayer { .. }) {
+            seen.push(message);
+        }
+        if done {
+            break;
+        }
+    }
+
+    let mut observer = server.connect(context)?;
+    // The game goes on meanwhile, so the observer may catch up with a few more turns.
+    for expected in &seen {
+        let message = observer.receive("the history of the game")?;
+        if &message != expected {
+            return Err(format!("Expected {:?}, got {:?}", expected, message));
+        }
+    }
+    server.check_alive()
+}
+
+fn multi_game(context: &Context) -> Outcome {
+    let parameters = GameParameters {
+        game_length: 6,
+        bomb_timer: 1,
+        explosion_radius: 3,
+        ..parameters(3)
+    };
+    let mut server = Server::start(context, parameters, 4242)?;
+    let mut clients = (0..4)
+        .map(|_| server.connect(context))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut trace = vec![TraceRecord::Server(server.parameters.hello())];
+    let scripts = [
+        "bomb up left\nleft bomb block\nup down bomb\nbomb bomb bomb\n",
+        "right right right\nbomb - block\n- bomb up\n",
+        "block bomb down\nup up up\nbomb bomb -\n",
+    ];
+    // The last client only watches.
+    for (game, script) in scripts.iter().enumerate() {
+        let script = Script::parse(script, 3).unwrap();
+        play_game(&mut clients, &names(3), &script, 6, &mut trace)
+            .map_err(|e| format!("Game {}: {}", game + 1, e))?;
+    }
+    check_session(context, "multi-game", &server, &trace)?;
+    server.check_alive()
+}
+
+fn main() {
+    let args = Args::parse();
+    let (list, names) = (args.list, args.scenarios.clone());
+    let code = harness::run_scenarios(SCENARIOS, list, &names, || Context {
+        server: args.server.clone().expect("required unless listing"),
+        timeout: Duration::from_millis(args.timeout),
+        args,
+    });
+    exit(code)
+}
@@ -0,0 +1,65 @@
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::exit;
+
+use clap::Parser;
+
+use robots::ascii::AsciiBoard;
+use robots::Position;
+
+/// Explodes all bombs on a board given in the README notation and prints the result.
+#[derive(Parser, Debug, Clone)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(short, long)]
+    explosion_radius: u16,
+
+    /// File with the board. It is read from standard input if absent.
+    board: Option<PathBuf>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut input = String::new();
+    let result = match &args.board {
+        Some(path) => {
+            std::fs::File::open(path).and_then(|mut file| file.read_to_string(&mut input))
+        }
+        None => std::io::stdin().read_to_string(&mut input),
+    };
+    if let Err(e) = result {
+        eprintln!("Cannot read the board: {}", e);
+        exit(1)
+    }
+
+    let before = input.parse::<AsciiBoard>().unwrap_or_else(|e| {
+        eprintln!("Invalid board: {}", e);
+        exit(1)
+    });
+    let explosion = before.explode(args.explosion_radius);
+
+    println!("{}", before.with_explosion(&explosion));
+    println!("{}", before.after_explosion(&explosion));
+
+    println!("Explosion: {}", format_positions(&explosion.hit));
+    println!(
+        "Blocks destroyed: {}",
+        format_positions(&explosion.blocks_destroyed)
+    );
+    let robots = explosion
+        .robots_destroyed
+        .iter()
+        .map(char::to_string)
+        .collect::<Vec<_>>();
+    println!("Robots destroyed: {}", robots.join(" "));
+}
+
+fn format_positions(positions: &BTreeSet<Position>) -> String {
+    positions
+        .iter()
+        .map(|position| format!("({}, {})", position.0, position.1))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
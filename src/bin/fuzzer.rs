@@ -0,0 +1,326 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::process::exit;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use thiserror::Error;
+
+use robots::harness::{self, Connection, HarnessError, Process};
+use robots::mutation::{self, MutatedStream, Validity};
+use robots::random::Random;
+use robots::{ClientMessage, ServerMessage};
+
+/// Sends malformed `ClientMessage` streams to a server on localhost and checks that it
+/// disconnects the sender, keeps the game going for the other clients and does not crash.
+#[derive(Parser, Debug, Clone)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Path to a `robots-server` executable to start. Without it, a server
+    /// already listening on `--port` is tested.
+    #[clap(required_unless_present = "port")]
+    server: Option<PathBuf>,
+
+    /// Port of a server which is already running.
+    #[clap(short, long)]
+    port: Option<u16>,
+
+    /// Number of randomly mutated streams, sent after the hand-picked ones.
+    #[clap(short = 'n', long, default_value_t = 100)]
+    iterations: u32,
+
+    /// Seed of the mutations.
+    #[clap(short, long, default_value_t = 1)]
+    seed: u32,
+
+    /// Turn duration passed to the server, in milliseconds.
+    #[clap(short = 'd', long, default_value_t = 50)]
+    turn_duration: u64,
+
+    /// How long to wait for the server to react, in milliseconds.
+    #[clap(short, long, default_value_t = 3000)]
+    timeout: u64,
+
+    /// Show the output of the server.
+    #[clap(short, long)]
+    verbose: bool,
+}
+
+/// Why a stream failed.
+#[derive(Debug, Error)]
+enum Failure {
+    /// The server is gone, so the run cannot go on.
+    #[error("Crash: {0}")]
+    Crash(String),
+    /// The game stopped, so the run cannot go on either.
+    #[error("Hang: {0}")]
+    Hang(String),
+    /// A player who behaves lost its connection.
+    #[error("{0}")]
+    Player(String),
+    /// The server reacted to the stream in the wrong way.
+    #[error("{0}")]
+    Reaction(String),
+    /// The fuzzer itself could not talk to the server.
+    #[error("{0}")]
+    Harness(String),
+}
+
+/// Players who behave, each on its own thread, counting the turns they receive.
+struct Players {
+    turns: Vec<Arc<AtomicU64>>,
+    failure: Arc<Mutex<Option<String>>>,
+}
+
+impl Players {
+    /// Connects `players_count` players, which join again after every game.
+    fn start(address: SocketAddr, players_count: u8, timeout: Duration) -> Result<Players, String> {
+        let failure = Arc::new(Mutex::new(None));
+        let mut turns = Vec::new();
+        for index in 0..players_count {
+            let mut connection = harness::connect(address, timeout)
+                .map_err(|e| format!("Cannot connect player {}: {}", index, e))?;
+            let counter = Arc::new(AtomicU64::new(0));
+            turns.push(counter.clone());
+            let failure = failure.clone();
+            thread::spawn(move || {
+                let error = play(&mut connection, index, &counter);
+                failure
+                    .lock()
+                    .unwrap()
+                    .get_or_insert(format!("Player {}: {}", index, error));
+            });
+        }
+        Ok(Players { turns, failure })
+    }
+
+    fn counts(&self) -> Vec<u64> {
+        self.turns
+            .iter()
+            .map(|turns| turns.load(Ordering::SeqCst))
+            .collect()
+    }
+
+    /// Waits until every player receives a turn it had not received before `since`.
+    fn check_progress(&self, since: &[u64], timeout: Duration) -> Result<(), Failure> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(failure) = self.failure.lock().unwrap().clone() {
+                return Err(Failure::Player(failure));
+            }
+            let counts = self.counts();
+            if counts.iter().zip(since).all(|(now, before)| now > before) {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(Failure::Hang(format!(
+                    "no Turn within {} ms (turns received: {:?} before, {:?} after)",
+                    timeout.as_millis(),
+                    since,
+                    counts
+                )));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+/// Plays until the connection fails, returning the reason.
+fn play(connection: &mut Connection, index: u8, turns: &AtomicU64) -> String {
+    let join = ClientMessage::Join {
+        name: format!("player{}", index).parse().unwrap(),
+    };
+    if let Err(e) = connection.send(&join) {
+        return format!("Cannot join: {}", e);
+    }
+    loop {
+        match connection.receive::<ServerMessage>(Duration::from_secs(3600)) {
+            Ok(ServerMessage::Turn { .. }) => {
+                turns.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(ServerMessage::GameEnded { .. }) => {
+                if let Err(e) = connection.send(&join) {
+                    return format!("Cannot join: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(HarnessError::Closed) => return "Disconnected by the server".to_string(),
+            Err(e) => return format!("Receiving: {}", e),
+        }
+    }
+}
+
+struct Target {
+    address: SocketAddr,
+    /// The server, if it was started by us.
+    process: Option<Process>,
+}
+
+impl Target {
+    fn start(args: &Args) -> Result<Target, String> {
+        let server = match &args.server {
+            None => {
+                return Ok(Target {
+                    address: (Ipv4Addr::LOCALHOST, args.port.expect("required")).into(),
+                    process: None,
+                })
+            }
+            Some(server) => server,
+        };
+        let port = match args.port {
+            Some(port) => port,
+            None => harness::free_tcp_port().map_err(|e| format!("No free port: {}", e))?,
+        };
+        let server_args = [
+            ("-b", "3".to_string()),
+            ("-c", "2".to_string()),
+            ("-d", args.turn_duration.to_string()),
+            ("-e", "2".to_string()),
+            ("-k", "10".to_string()),
+            ("-l", "100".to_string()),
+            ("-n", "fuzzer".to_string()),
+            ("-p", port.to_string()),
+            ("-s", "1".to_string()),
+            ("-x", "10".to_string()),
+            ("-y", "10".to_string()),
+        ]
+        .into_iter()
+        .flat_map(|(flag, value)| [flag.to_string(), value])
+        .collect::<Vec<_>>();
+        let process = Process::spawn(server, &server_args, args.verbose)
+            .map_err(|e| format!("Cannot start {}: {}", server.display(), e))?;
+        Ok(Target {
+            address: (Ipv4Addr::LOCALHOST, port).into(),
+            process: Some(process),
+        })
+    }
+
+    /// Checks that the server still runs. A server started elsewhere has to accept a connection.
+    fn check_alive(&mut self, timeout: Duration) -> Result<(), Failure> {
+        match &mut self.process {
+            Some(process) => match process.exit_status() {
+                Ok(None) => Ok(()),
+                Ok(Some(status)) => Err(Failure::Crash(format!("server exited with {}", status))),
+                Err(e) => Err(Failure::Harness(format!("Cannot check the server: {}", e))),
+            },
+            None => harness::connect(self.address, timeout)
+                .map(|_| ())
+                .map_err(|e| Failure::Crash(format!("cannot connect any more: {}", e))),
+        }
+    }
+}
+
+fn send_stream(
+    target: &Target,
+    players: &Players,
+    stream: &MutatedStream,
+    args: &Args,
+) -> Result<(), Failure> {
+    let timeout = Duration::from_millis(args.timeout);
+    let mut connection = harness::connect(target.address, timeout)
+        .map_err(|e| Failure::Harness(format!("Cannot connect: {}", e)))?;
+    let before = players.counts();
+    connection
+        .send_bytes(&stream.bytes)
+        .map_err(|e| Failure::Harness(format!("Cannot send: {}", e)))?;
+    // Only the end of the stream shows that the last message is incomplete.
+    if stream.validity() == Validity::Incomplete {
+        connection
+            .shutdown_write()
+            .map_err(|e| Failure::Harness(format!("Cannot close the stream: {}", e)))?;
+    }
+
+    if stream.expect_disconnect() {
+        let closed = connection
+            .closed_within(timeout)
+            .map_err(|e| Failure::Harness(format!("Receiving: {}", e)))?;
+        if !closed {
+            return Err(Failure::Reaction(format!(
+                "Connection still open {} ms after the malformed stream",
+                timeout.as_millis()
+            )));
+        }
+    } else {
+        // Waiting for a few turns is enough to tell a disconnect apart from a slow server.
+        let wait = Duration::from_millis(args.turn_duration * 3);
+        if connection
+            .closed_within(wait)
+            .map_err(|e| Failure::Harness(format!("Receiving: {}", e)))?
+        {
+            return Err(Failure::Reaction(
+                "Connection closed after a valid stream".to_string(),
+            ));
+        }
+    }
+
+    players.check_progress(&before, timeout)
+}
+
+fn main() {
+    let args = Args::parse();
+    let timeout = Duration::from_millis(args.timeout);
+
+    let mut target = Target::start(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(2)
+    });
+    let players_count = match harness::connect(target.address, timeout)
+        .and_then(|mut connection| connection.receive(timeout))
+    {
+        Ok(ServerMessage::Hello { players_count, .. }) => players_count,
+        Ok(other) => {
+            eprintln!("Expected Hello, got {:?}", other);
+            exit(2)
+        }
+        Err(e) => {
+            eprintln!("Cannot connect to {}: {}", target.address, e);
+            exit(2)
+        }
+    };
+    let players = Players::start(target.address, players_count, timeout).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(2)
+    });
+    if let Err(e) = players.check_progress(&players.counts(), timeout) {
+        eprintln!("The game does not start: {}", e);
+        exit(2)
+    }
+
+    let mut random = Random::new(args.seed);
+    let streams = mutation::catalogue().into_iter().chain(
+        (0..args.iterations as usize).map(|index| mutation::random_stream(&mut random, index)),
+    );
+    let (mut passed, mut failed) = (0, 0);
+    for stream in streams {
+        let result = send_stream(&target, &players, &stream, &args);
+        // A crash disconnects the players too, so it is reported instead of their failure.
+        // The process may need a moment to be reaped after its sockets are closed.
+        if result.is_err() {
+            thread::sleep(Duration::from_millis(100));
+        }
+        let crash = target.check_alive(timeout).err();
+        match (crash, result) {
+            (None, Ok(())) => {
+                passed += 1;
+                println!("ok   {}", stream.name);
+            }
+            (crash, result) => {
+                failed += 1;
+                let failure = crash.or(result.err()).expect("one of them failed");
+                println!("FAIL {} {:02x?}: {}", stream.name, stream.bytes, failure);
+                // Nothing more can be learned from a server which is gone or stuck.
+                match failure {
+                    Failure::Crash(_) | Failure::Hang(_) => break,
+                    Failure::Player(_) | Failure::Reaction(_) | Failure::Harness(_) => {}
+                }
+            }
+        }
+    }
+    println!("{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        exit(1)
+    }
+}
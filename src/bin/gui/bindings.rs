@@ -0,0 +1,457 @@
+//! Key bindings, grouped in profiles. Each profile sends its input to its own client,
+//! so that two people can share a keyboard. Keys are named like bevy's `KeyCode`s
+//! (`W`, `Up`, `Space`), or given as `scan:<code>` to use the same physical key
+//! whatever the keyboard layout.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use robots::{Direction, InputMessage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Bomb,
+    Block,
+}
+
+impl Action {
+    pub fn message(self) -> InputMessage {
+        let direction = match self {
+            Action::Bomb => return InputMessage::PlaceBomb,
+            Action::Block => return InputMessage::PlaceBlock,
+            Action::Up => Direction::Up,
+            Action::Down => Direction::Down,
+            Action::Left => Direction::Left,
+            Action::Right => Direction::Right,
+        };
+        InputMessage::Move { direction }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_string()))
+            .map_err(|_| format!("Unknown action {}", s))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Key {
+    /// What the key produces in the current layout.
+    Code(KeyCode),
+    /// Where the key is on the keyboard.
+    Scan(u32),
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("scan:") {
+            Some(code) => code
+                .parse()
+                .map(Key::Scan)
+                .map_err(|_| format!("Invalid scan code {}", code)),
+            None => serde_json::from_value(serde_json::Value::String(s.to_string()))
+                .map(Key::Code)
+                .map_err(|_| format!("Unknown key {}", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Code(code) => write!(f, "{:?}", code),
+            Key::Scan(code) => write!(f, "scan:{}", code),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BindingsError {
+    #[error("Cannot read {}: {source}", .path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid bindings in {}: {source}", .path.display())]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("No profile named {0}")]
+    UnknownProfile(String),
+    #[error("Invalid client address {address} of profile {profile}: {reason}")]
+    Address {
+        profile: String,
+        address: String,
+        reason: String,
+    },
+    #[error("Invalid binding {binding}: {reason}, expected ACTION=KEY[,KEY...]")]
+    Bind { binding: String, reason: String },
+    #[error("Key {key} is bound to {first} and {second}")]
+    Conflict {
+        key: Key,
+        first: String,
+        second: String,
+    },
+}
+
+/// A profile as written in the bindings file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileConfig {
+    /// The client started with `--client-address` if missing.
+    client_address: Option<String>,
+    bindings: BTreeMap<Action, Vec<Key>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub client_address: SocketAddr,
+    pub keys: BTreeMap<Action, Vec<Key>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    pub profiles: Vec<Profile>,
+}
+
+impl Bindings {
+    /// The keys of a single player: WSAD or arrows, Space, J or Z for bombs, K or X for blocks.
+    pub fn default_profile(client_address: SocketAddr) -> Profile {
+        let keys = |codes: &[KeyCode]| codes.iter().copied().map(Key::Code).collect();
+        Profile {
+            name: "default".to_string(),
+            client_address,
+            keys: BTreeMap::from([
+                (Action::Up, keys(&[KeyCode::W, KeyCode::Up])),
+                (Action::Down, keys(&[KeyCode::S, KeyCode::Down])),
+                (Action::Left, keys(&[KeyCode::A, KeyCode::Left])),
+                (Action::Right, keys(&[KeyCode::D, KeyCode::Right])),
+                (
+                    Action::Bomb,
+                    keys(&[KeyCode::Space, KeyCode::J, KeyCode::Z]),
+                ),
+                (Action::Block, keys(&[KeyCode::K, KeyCode::X])),
+            ]),
+        }
+    }
+
+    /// Reads the chosen profiles from the file, all of them if none is chosen, or takes
+    /// the default profile without a file. `binds` then replace keys of the first profile.
+    pub fn load(
+        path: Option<&Path>,
+        chosen: &[String],
+        binds: &[String],
+        client_address: SocketAddr,
+    ) -> Result<Bindings, BindingsError> {
+        let mut bindings = match path {
+            None => match chosen.first() {
+                Some(name) => return Err(BindingsError::UnknownProfile(name.clone())),
+                None => Bindings {
+                    profiles: vec![Self::default_profile(client_address)],
+                },
+            },
+            Some(path) => {
+                let text = std::fs::read_to_string(path).map_err(|source| BindingsError::Read {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+                Self::parse(&text, path, chosen, client_address)?
+            }
+        };
+        for bind in binds {
+            bindings.bind(bind)?;
+        }
+        bindings.check_conflicts()?;
+        Ok(bindings)
+    }
+
+    fn parse(
+        text: &str,
+        path: &Path,
+        chosen: &[String],
+        client_address: SocketAddr,
+    ) -> Result<Bindings, BindingsError> {
+        let mut config: BTreeMap<String, ProfileConfig> =
+            serde_json::from_str(text).map_err(|source| BindingsError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        let names = if chosen.is_empty() {
+            config.keys().cloned().collect()
+        } else {
+            chosen.to_vec()
+        };
+        let profiles = names
+            .into_iter()
+            .map(|name| {
+                let profile = config
+                    .remove(&name)
+                    .ok_or_else(|| BindingsError::UnknownProfile(name.clone()))?;
+                let client_address = match profile.client_address {
+                    None => client_address,
+                    Some(address) => {
+                        crate::parse_addr(&address).map_err(|reason| BindingsError::Address {
+                            profile: name.clone(),
+                            address,
+                            reason,
+                        })?
+                    }
+                };
+                Ok(Profile {
+                    name,
+                    client_address,
+                    keys: profile.bindings,
+                })
+            })
+            .collect::<Result<_, BindingsError>>()?;
+        Ok(Bindings { profiles })
+    }
+
+    /// Applies `ACTION=KEY[,KEY...]` to the first profile.
+    fn bind(&mut self, bind: &str) -> Result<(), BindingsError> {
+        let error = |reason: String| BindingsError::Bind {
+            binding: bind.to_string(),
+            reason,
+        };
+        let (action, keys) = bind
+            .split_once('=')
+            .ok_or_else(|| error("missing =".to_string()))?;
+        let action = action.trim().parse::<Action>().map_err(error)?;
+        let keys = keys
+            .split(',')
+            .map(|key| key.trim().parse::<Key>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(error)?;
+        let profile = self
+            .profiles
+            .first_mut()
+            .ok_or_else(|| error("no profile".to_string()))?;
+        profile.keys.insert(action, keys);
+        Ok(())
+    }
+
+    fn check_conflicts(&self) -> Result<(), BindingsError> {
+        let mut seen = BTreeMap::<String, String>::new();
+        for profile in &self.profiles {
+            for (action, keys) in &profile.keys {
+                for key in keys {
+                    let what = format!("{} {:?}", profile.name, action);
+                    if let Some(first) = seen.insert(key.to_string(), what.clone()) {
+                        return Err(BindingsError::Conflict {
+                            key: *key,
+                            first,
+                            second: what,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The profile and action of a pressed key, looked up by key code and then by scan code.
+    pub fn lookup(&self, key_code: Option<KeyCode>, scan_code: u32) -> Option<(&Profile, Action)> {
+        let find = |wanted: Key| {
+            self.profiles.iter().find_map(|profile| {
+                profile
+                    .keys
+                    .iter()
+                    .find(|(_, keys)| keys.contains(&wanted))
+                    .map(|(action, _)| (profile, *action))
+            })
+        };
+        key_code
+            .and_then(|code| find(Key::Code(code)))
+            .or_else(|| find(Key::Scan(scan_code)))
+    }
+
+    /// A line per action of every profile, for the overlay.
+    pub fn describe(&self) -> String {
+        let mut text = String::new();
+        for profile in &self.profiles {
+            text += &format!("{} -> {}\n", profile.name, profile.client_address);
+            for (action, keys) in &profile.keys {
+                let keys = keys.iter().map(Key::to_string).collect::<Vec<_>>();
+                text += &format!("  {:?}: {}\n", action, keys.join(", "));
+            }
+        }
+        text
+    }
+}
+
+/// Key which shows and hides the list of bindings.
+const OVERLAY_KEY: KeyCode = KeyCode::F1;
+
+/// The list of bindings, in the top-right corner of the window.
+#[derive(Component)]
+pub struct BindingsOverlay;
+
+pub fn setup_overlay(
+    mut commands: Commands,
+    bindings: Res<Bindings>,
+    loaded_font: Res<crate::LoadedFont>,
+) {
+    let text = format!(
+        "{}\nView: wheel, +, - zoom, right drag or Shift+move pans,\n0 fits the board, F follows a player\n\nF1 hides this list",
+        bindings.describe()
+    );
+    let hidden = Visibility { is_visible: false };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    ..default()
+                },
+                padding: Rect::all(Val::Px(8.0)),
+                ..default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.85).into(),
+            visibility: hidden.clone(),
+            ..default()
+        })
+        .insert(BindingsOverlay)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        text,
+                        TextStyle {
+                            font: loaded_font.0.clone(),
+                            font_size: 14.0,
+                            color: Color::WHITE,
+                        },
+                        default(),
+                    ),
+                    visibility: hidden,
+                    ..default()
+                })
+                .insert(BindingsOverlay);
+        });
+}
+
+pub fn toggle_overlay(
+    keys: Res<Input<KeyCode>>,
+    mut overlay: Query<&mut Visibility, With<BindingsOverlay>>,
+) {
+    if keys.just_pressed(OVERLAY_KEY) {
+        for mut visibility in overlay.iter_mut() {
+            visibility.is_visible = !visibility.is_visible;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+        "left": {
+            "client_address": "127.0.0.1:10022",
+            "bindings": {"up": ["W"], "down": ["S"], "bomb": ["Space", "scan:42"]}
+        },
+        "right": {
+            "bindings": {"up": ["Up"], "down": ["Down"], "bomb": ["Return"]}
+        }
+    }"#;
+
+    fn address() -> SocketAddr {
+        "[::1]:10023".parse().unwrap()
+    }
+
+    #[test]
+    fn profiles() {
+        let bindings = Bindings::parse(CONFIG, Path::new("bindings.json"), &[], address()).unwrap();
+        assert_eq!(bindings.profiles.len(), 2);
+        let (left, action) = bindings.lookup(Some(KeyCode::W), 17).unwrap();
+        assert_eq!(
+            (left.name.as_str(), left.client_address, action),
+            ("left", "127.0.0.1:10022".parse().unwrap(), Action::Up)
+        );
+        let (right, action) = bindings.lookup(Some(KeyCode::Return), 28).unwrap();
+        assert_eq!((right.client_address, action), (address(), Action::Bomb));
+        // Scan codes match whatever the key produces.
+        let (left, action) = bindings.lookup(None, 42).unwrap();
+        assert_eq!((left.name.as_str(), action), ("left", Action::Bomb));
+        assert_eq!(bindings.lookup(Some(KeyCode::Q), 16), None);
+
+        let chosen = Bindings::parse(
+            CONFIG,
+            Path::new("bindings.json"),
+            &["right".to_string()],
+            address(),
+        )
+        .unwrap();
+        assert_eq!(chosen.profiles.len(), 1);
+        assert!(matches!(
+            Bindings::parse(
+                CONFIG,
+                Path::new("bindings.json"),
+                &["middle".to_string()],
+                address()
+            ),
+            Err(BindingsError::UnknownProfile(_))
+        ));
+    }
+
+    #[test]
+    fn command_line_binds() {
+        let mut bindings = Bindings {
+            profiles: vec![Bindings::default_profile(address())],
+        };
+        bindings.bind("bomb = B, scan:57").unwrap();
+        assert_eq!(
+            bindings.profiles[0].keys[&Action::Bomb],
+            vec![Key::Code(KeyCode::B), Key::Scan(57)]
+        );
+        assert_eq!(
+            bindings.lookup(Some(KeyCode::Space), 57).unwrap().1,
+            Action::Bomb
+        );
+        assert!(bindings.bind("jump=Space").is_err());
+        assert!(bindings.bind("up=Nope").is_err());
+        assert!(bindings.bind("up").is_err());
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut bindings =
+            Bindings::parse(CONFIG, Path::new("bindings.json"), &[], address()).unwrap();
+        bindings.check_conflicts().unwrap();
+        bindings.bind("block=Down").unwrap();
+        assert_eq!(
+            bindings.check_conflicts().unwrap_err().to_string(),
+            "Key Down is bound to left Block and right Down"
+        );
+    }
+}
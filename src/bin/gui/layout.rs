@@ -0,0 +1,137 @@
+//! Where the board and the panels go in the window. The side panel takes a column on the left,
+//! the board gets the rest and keeps its cells square.
+
+use bevy::math::Vec2;
+
+use robots::Position;
+
+/// Width of the column with the server info, scores and diagnostics, in pixels.
+/// Narrow windows give it at most a third of their width.
+const SIDE_PANEL_WIDTH: f32 = 240.0;
+
+/// Space around the panel and the board.
+pub const MARGIN: f32 = 10.0;
+
+/// Space left and below the board for the coordinate rulers.
+pub const RULER: f32 = 18.0;
+
+/// The origin of the world is the centre of the window, `y` grows upwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub window: Vec2,
+    pub size_x: u16,
+    pub size_y: u16,
+    /// Side of a cell.
+    pub cell: f32,
+    /// Bottom-left corner of the space for the board, which is centred in it.
+    pub area_origin: Vec2,
+    pub area_size: Vec2,
+    /// Bottom-left corner of the board.
+    pub board_origin: Vec2,
+    /// Top-left corner of the side panel.
+    pub panel_origin: Vec2,
+    pub panel_size: Vec2,
+}
+
+impl Layout {
+    pub fn new(window: Vec2, size_x: u16, size_y: u16) -> Layout {
+        let half = window / 2.0;
+        let panel_width = SIDE_PANEL_WIDTH.min(window.x / 3.0);
+        let panel_origin = Vec2::new(-half.x + MARGIN, half.y - MARGIN);
+        let panel_size = Vec2::new(panel_width, window.y - 2.0 * MARGIN).max(Vec2::ZERO);
+
+        let area_origin = Vec2::new(
+            -half.x + panel_width + 2.0 * MARGIN + RULER,
+            -half.y + MARGIN + RULER,
+        );
+        let area = Vec2::new(
+            window.x - panel_width - 3.0 * MARGIN - RULER,
+            window.y - 2.0 * MARGIN - RULER,
+        )
+        .max(Vec2::ZERO);
+        let cells = Vec2::new(size_x.max(1) as f32, size_y.max(1) as f32);
+        let cell = (area.x / cells.x).min(area.y / cells.y);
+        let board_origin = area_origin + (area - cells * cell) / 2.0;
+        Layout {
+            window,
+            size_x,
+            size_y,
+            cell,
+            area_origin,
+            area_size: area,
+            board_origin,
+            panel_origin,
+            panel_size,
+        }
+    }
+
+    pub fn board_size(&self) -> Vec2 {
+        Vec2::new(self.size_x as f32, self.size_y as f32) * self.cell
+    }
+
+    pub fn area_centre(&self) -> Vec2 {
+        self.area_origin + self.area_size / 2.0
+    }
+
+    /// Centre of the cell.
+    pub fn cell_centre(&self, position: Position) -> Vec2 {
+        self.board_origin + (Vec2::new(position.0 as f32, position.1 as f32) + 0.5) * self.cell
+    }
+
+    /// Top-left corner of the cell, where the shapes drawn on it are anchored.
+    pub fn cell_top_left(&self, position: Position) -> Vec2 {
+        self.board_origin + Vec2::new(position.0 as f32, position.1 as f32 + 1.0) * self.cell
+    }
+
+    /// Bottom-left corner of the side panel.
+    pub fn panel_bottom_left(&self) -> Vec2 {
+        self.panel_origin - Vec2::new(0.0, self.panel_size.y)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_fits(layout: &Layout) {
+        let board_end = layout.board_origin + layout.board_size();
+        let half = layout.window / 2.0;
+        assert!(layout.board_origin.x >= layout.panel_origin.x + layout.panel_size.x + RULER);
+        assert!(layout.board_origin.y >= -half.y + RULER);
+        assert!(board_end.x <= half.x + 1e-3 && board_end.y <= half.y + 1e-3);
+    }
+
+    #[test]
+    fn wide_board_keeps_square_cells() {
+        let layout = Layout::new(Vec2::new(800.0, 600.0), 40, 5);
+        assert_fits(&layout);
+        let board = layout.board_size();
+        assert!((board.x / board.y - 8.0).abs() < 1e-3);
+        // Limited by the width left of the panel.
+        assert!((board.x - (800.0 - 240.0 - 3.0 * MARGIN - RULER)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn tall_board_is_centred() {
+        let layout = Layout::new(Vec2::new(1000.0, 500.0), 5, 20);
+        assert_fits(&layout);
+        assert!((layout.board_size().y - (500.0 - 2.0 * MARGIN - RULER)).abs() < 1e-3);
+        let area_centre = (-500.0 + 240.0 + 2.0 * MARGIN + RULER + 500.0 - MARGIN) / 2.0;
+        let board_centre = layout.board_origin.x + layout.board_size().x / 2.0;
+        assert!((board_centre - area_centre).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cells() {
+        let layout = Layout::new(Vec2::new(818.0, 538.0), 10, 10);
+        assert_eq!(layout.cell, 50.0);
+        assert_eq!(
+            layout.cell_top_left(Position(0, 0)),
+            layout.board_origin + Vec2::new(0.0, 50.0)
+        );
+        assert_eq!(
+            layout.cell_top_left(Position(9, 9)) + Vec2::new(50.0, -50.0),
+            layout.board_origin + layout.board_size() - Vec2::new(0.0, 50.0)
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use bevy::input::keyboard::KeyboardInput;
+use bevy::prelude::*;
+use bevy::window::WindowResizeConstraints;
+use bevy_prototype_lyon::prelude::*;
+use crossbeam_channel::{bounded, Receiver};
+use std::collections::HashMap;
+use std::net::{Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Parser;
+use lazy_static::lazy_static;
+use robots::{DisplayMessage, MAX_UDP_LENGTH};
+use robots::serialize::{deserializer, serializer};
+
+use bindings::{Action, Bindings};
+use pacing::{Pacer, Repeat, TurnClock};
+use scene::{Panel, Scene};
+use view::View;
+
+mod bindings;
+mod layout;
+mod pacing;
+mod results;
+mod route;
+mod scene;
+mod view;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(short, long, parse(try_from_str = parse_addr))]
+    client_address: SocketAddr,
+
+    #[clap(short, long)]
+    port: u16,
+
+    /// JSON file with profiles of key bindings, see the README.
+    #[clap(short, long)]
+    bindings: Option<PathBuf>,
+
+    /// Profile from the bindings file to use, may be repeated. All of them by default.
+    #[clap(long = "profile")]
+    profiles: Vec<String>,
+
+    /// Keys of an action of the first profile, e.g. `bomb=Space,scan:57`. May be repeated.
+    #[clap(long = "bind")]
+    binds: Vec<String>,
+
+    /// Send held keys again every this many milliseconds, or once per turn with `turn`.
+    /// Presses within an interval are sent as a single message. Off by default.
+    #[clap(short, long)]
+    repeat: Option<Repeat>,
+
+    /// Turn duration of the server in milliseconds, as given to it with `-d`.
+    /// `--repeat turn` then sends once per turn from the start instead of measuring turns.
+    #[clap(long)]
+    turn_duration: Option<u64>,
+
+    /// Name of the player driven by the first profile, whose robot moves to the clicked cell.
+    /// Without it, the robot of the followed player moves.
+    #[clap(short = 'n', long)]
+    player_name: Option<String>,
+
+    /// Seconds the results of a game stay on screen before the lobby, unless a key is pressed.
+    /// 0 goes straight to the lobby.
+    #[clap(long, default_value_t = 10)]
+    results: u64,
+}
+
+fn parse_addr(s: &str) -> Result<SocketAddr, String> {
+    s.to_socket_addrs()
+        .map_err(|e| e.to_string())
+        .and_then(|mut iter| iter.next().ok_or_else(|| "No address found".to_string()))
+}
+
+lazy_static! {
+    static ref ARGS: Args = Args::parse();
+}
+
+fn main() {
+    info!(args = ?ARGS.clone());
+    let bindings = Bindings::load(
+        ARGS.bindings.as_deref(),
+        &ARGS.profiles,
+        &ARGS.binds,
+        ARGS.client_address,
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+
+    App::new()
+        .insert_resource(WindowDescriptor {
+            title: "Robots!".to_string(),
+            width: 900.,
+            height: 600.,
+            resizable: true,
+            resize_constraints: WindowResizeConstraints {
+                min_width: 480.,
+                min_height: 360.,
+                ..default()
+            },
+            ..default()
+        })
+        .insert_resource(bevy::log::LogSettings {
+            level: bevy::log::Level::INFO,
+            filter: "wgpu=warn,wgpu_core=warn,bevy_ecs=info".to_string(),
+        })
+        .add_event::<DisplayMessage>()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(ShapePlugin)
+        .init_resource::<Scene>()
+        .init_resource::<View>()
+        .insert_resource(bindings)
+        .insert_resource(
+            ARGS.turn_duration
+                .map(|millis| TurnClock::given(Duration::from_millis(millis)))
+                .unwrap_or_default(),
+        )
+        .init_resource::<route::Router>()
+        .add_startup_system(setup)
+        .add_startup_system(scene::setup)
+        .add_startup_system_to_stage(StartupStage::PostStartup, bindings::setup_overlay)
+        .add_system(read_stream)
+        .add_system(scene::draw)
+        .add_system(results::expire.after("send_input"))
+        .add_system(scene::pulse_bombs)
+        .add_system(view::control)
+        .add_system(view::draw_overlay)
+        .add_system(pacing::measure_turns)
+        .add_system(send_input.label("send_input"))
+        .add_system(route::click)
+        .add_system(route::follow_route)
+        .add_system(route::draw_route)
+        .add_system(bindings::toggle_overlay)
+        .run();
+}
+
+#[derive(Deref)]
+struct DisplayMessageReceiver(Receiver<DisplayMessage>);
+
+#[derive(Deref)]
+struct InputMessageSender(UdpSocket);
+
+#[derive(Deref)]
+struct LoadedFont(Handle<Font>);
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let socket = UdpSocket::bind(SocketAddr::from((Ipv6Addr::UNSPECIFIED, ARGS.port))).unwrap();
+    let socket_clone = socket.try_clone().unwrap();
+
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    let (display_tx, display_rx) = bounded::<DisplayMessage>(10);
+    std::thread::spawn(move || loop {
+        let mut buf = Box::new([0; MAX_UDP_LENGTH]);
+        match socket.recv_from(&mut *buf) {
+            Ok((amt, _src)) => {
+                let message = deserializer::from_bytes::<DisplayMessage>(&buf[0..amt]);
+                match message {
+                    Ok(message) => {
+                        info!(?message, "Received message");
+                        display_tx.send(message).unwrap()
+                    }
+                    Err(e) => error!("{:?}", e),
+                }
+            }
+            Err(e) => error!("{}", e),
+        }
+    });
+
+    commands.insert_resource(InputMessageSender(socket_clone));
+    commands.insert_resource(DisplayMessageReceiver(display_rx));
+
+    let loaded_font = asset_server.load("fonts/FiraSans-Medium.ttf");
+    commands.insert_resource(LoadedFont(loaded_font.clone()));
+
+    let text_style = TextStyle {
+        font: loaded_font,
+        font_size: 25.0,
+        color: Color::RED,
+    };
+    let text_alignment = TextAlignment {
+        vertical: VerticalAlign::Top,
+        horizontal: HorizontalAlign::Center,
+    };
+    let text = format!("Waiting for input from client ({}) on port {}", ARGS.client_address, ARGS.port);
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section(text, text_style, text_alignment),
+            transform: Transform::from_xyz(0.0, 0.0, 1.0),
+            ..default()
+        })
+        .insert(Panel::Status);
+}
+
+// This system reads from the receiver and sends events to Bevy
+fn read_stream(receiver: ResMut<DisplayMessageReceiver>, mut events: EventWriter<DisplayMessage>) {
+    for message in receiver.try_iter() {
+        events.send(message);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn send_input(
+    socket: ResMut<InputMessageSender>,
+    bindings: Res<Bindings>,
+    mut keyboard_input_events: EventReader<KeyboardInput>,
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    clock: Res<TurnClock>,
+    scene: Res<Scene>,
+    mut pacers: Local<HashMap<String, Pacer>>,
+) {
+    // A key press dismisses the results. The client is already in the lobby,
+    // so it would take the same press as a Join.
+    if scene.results().is_some() {
+        keyboard_input_events.iter().for_each(drop);
+        pacers.clear();
+        return;
+    }
+
+    let send = |action: Action, client_address: SocketAddr| {
+        let input_message = action.message();
+        info!("Sending {:?} to {}", input_message, client_address);
+        match socket
+            .0
+            .send_to(&serializer::to_bytes(input_message), client_address)
+        {
+            Ok(amt) => info!("Sent {} bytes", amt),
+            Err(e) => error!("{}", e),
+        }
+    };
+
+    // With Shift the movement keys pan the view instead.
+    let panning = keys.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+    for event in keyboard_input_events.iter() {
+        let pressed = event.state.is_pressed();
+        if panning && pressed {
+            continue;
+        }
+        let (profile, action) = match bindings.lookup(event.key_code, event.scan_code) {
+            Some(found) => found,
+            None => continue,
+        };
+        match ARGS.repeat {
+            None if pressed => send(action, profile.client_address),
+            None => {}
+            Some(_) => {
+                let pacer = pacers.entry(profile.name.clone()).or_default();
+                if pressed {
+                    pacer.press(event.scan_code, action);
+                } else {
+                    pacer.release(event.scan_code);
+                }
+            }
+        }
+    }
+
+    if let Some(repeat) = ARGS.repeat {
+        let (now, interval) = (time.seconds_since_startup(), repeat.interval(&clock));
+        for profile in &bindings.profiles {
+            if let Some(action) = pacers
+                .get_mut(&profile.name)
+                .and_then(|pacer| pacer.poll(now, interval))
+            {
+                send(action, profile.client_address);
+            }
+        }
+    }
+}
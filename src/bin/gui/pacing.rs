@@ -0,0 +1,211 @@
+//! Optional pacing of the input. The server only takes the last message of each turn
+//! into account, so instead of a datagram per key press, each profile sends at most one
+//! message per interval: the last key pressed during it, or else the key still held.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use robots::DisplayMessage;
+
+use crate::bindings::Action;
+
+/// Interval used with `--repeat turn` without `--turn-duration` until two turns have been seen.
+const FALLBACK_INTERVAL: f64 = 0.2;
+
+/// Weight of the latest measurement in the estimated turn duration.
+const TURN_SMOOTHING: f64 = 0.3;
+
+/// How often held keys are repeated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Repeat {
+    Every(Duration),
+    /// Once per turn, as given with `--turn-duration` or else measured from the messages
+    /// of the client. Neither `Hello` nor `Lobby` carry the turn duration.
+    Turn,
+}
+
+impl Repeat {
+    /// In seconds.
+    pub fn interval(self, clock: &TurnClock) -> f64 {
+        match self {
+            Repeat::Every(interval) => interval.as_secs_f64(),
+            Repeat::Turn => clock.duration.unwrap_or(FALLBACK_INTERVAL),
+        }
+    }
+}
+
+impl FromStr for Repeat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "turn" {
+            return Ok(Repeat::Turn);
+        }
+        match s.parse::<u64>() {
+            Ok(millis) if millis > 0 => Ok(Repeat::Every(Duration::from_millis(millis))),
+            _ => Err(format!(
+                "Expected `turn` or a positive number of milliseconds, got {}",
+                s
+            )),
+        }
+    }
+}
+
+/// The input of one profile waiting to be sent.
+#[derive(Debug, Default)]
+pub struct Pacer {
+    /// Time of the next message, in seconds. Sending waits until then.
+    next: f64,
+    /// The last key pressed since the previous message.
+    pending: Option<Action>,
+    /// Keys still down, by scan code, in the order they were pressed.
+    held: Vec<(u32, Action)>,
+}
+
+impl Pacer {
+    pub fn press(&mut self, scan_code: u32, action: Action) {
+        // Repeated presses of a held key come from the system's key repeat.
+        if self.held.iter().any(|(held, _)| *held == scan_code) {
+            return;
+        }
+        self.pending = Some(action);
+        self.held.push((scan_code, action));
+    }
+
+    pub fn release(&mut self, scan_code: u32) {
+        self.held.retain(|(held, _)| *held != scan_code);
+    }
+
+    /// The action to send now, if any. The first one after a pause goes out immediately.
+    pub fn poll(&mut self, now: f64, interval: f64) -> Option<Action> {
+        if now < self.next {
+            return None;
+        }
+        let action = self
+            .pending
+            .take()
+            .or_else(|| self.held.last().map(|(_, action)| *action))?;
+        self.next = now + interval;
+        Some(action)
+    }
+}
+
+/// Duration of a turn of the game being shown, given or estimated.
+#[derive(Debug, Default)]
+pub struct TurnClock {
+    /// The last turn seen and when its first message arrived.
+    last: Option<(u16, f64)>,
+    /// In seconds.
+    pub duration: Option<f64>,
+    /// Whether the duration was given, in which case it is not estimated.
+    given: bool,
+}
+
+impl TurnClock {
+    /// A clock with the turn duration of the server, which holds for every game.
+    pub fn given(duration: Duration) -> TurnClock {
+        TurnClock {
+            last: None,
+            duration: Some(duration.as_secs_f64()),
+            given: true,
+        }
+    }
+
+    pub fn observe(&mut self, turn: u16, now: f64) {
+        if self.given {
+            return;
+        }
+        match self.last {
+            Some((last, since)) if turn > last => {
+                let measured = (now - since) / (turn - last) as f64;
+                self.duration = Some(match self.duration {
+                    Some(duration) => duration + (measured - duration) * TURN_SMOOTHING,
+                    None => measured,
+                });
+            }
+            // More messages in the same turn.
+            Some((last, _)) if turn == last => return,
+            // A new game, or the first message.
+            _ => {}
+        }
+        self.last = Some((turn, now));
+    }
+}
+
+pub fn measure_turns(
+    mut reader: EventReader<DisplayMessage>,
+    time: Res<Time>,
+    mut clock: ResMut<TurnClock>,
+) {
+    for event in reader.iter() {
+        if let DisplayMessage::Game { turn, .. } = event {
+            clock.observe(*turn, time.seconds_since_startup());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn presses_within_an_interval_are_coalesced() {
+        let mut pacer = Pacer::default();
+        pacer.press(17, Action::Up);
+        pacer.release(17);
+        assert_eq!(pacer.poll(0.0, 0.1), Some(Action::Up));
+        pacer.press(30, Action::Left);
+        pacer.release(30);
+        pacer.press(57, Action::Bomb);
+        pacer.release(57);
+        assert_eq!(pacer.poll(0.05, 0.1), None);
+        assert_eq!(pacer.poll(0.1, 0.1), Some(Action::Bomb));
+        assert_eq!(pacer.poll(0.2, 0.1), None);
+        // After a pause the next press is sent at once.
+        pacer.press(17, Action::Up);
+        assert_eq!(pacer.poll(0.73, 0.1), Some(Action::Up));
+    }
+
+    #[test]
+    fn held_keys_repeat() {
+        let mut pacer = Pacer::default();
+        pacer.press(17, Action::Up);
+        assert_eq!(pacer.poll(0.0, 0.25), Some(Action::Up));
+        pacer.press(17, Action::Up);
+        assert_eq!(pacer.poll(0.25, 0.25), Some(Action::Up));
+        pacer.press(32, Action::Right);
+        assert_eq!(pacer.poll(0.5, 0.25), Some(Action::Right));
+        pacer.release(32);
+        assert_eq!(pacer.poll(0.75, 0.25), Some(Action::Up));
+        pacer.release(17);
+        assert_eq!(pacer.poll(1.0, 0.25), None);
+    }
+
+    #[test]
+    fn turn_duration() {
+        let mut clock = TurnClock::default();
+        clock.observe(0, 1.0);
+        clock.observe(0, 1.05);
+        assert_eq!(clock.duration, None);
+        clock.observe(2, 1.5);
+        assert_eq!(clock.duration, Some(0.25));
+        clock.observe(3, 1.6);
+        assert!((clock.duration.unwrap() - 0.205).abs() < 1e-9);
+        // A new game keeps the estimate.
+        clock.observe(0, 5.0);
+        assert!(clock.duration.is_some());
+
+        // A given duration is used from the start and is not replaced by estimates.
+        let mut clock = TurnClock::given(Duration::from_millis(1000));
+        assert_eq!(Repeat::Turn.interval(&clock), 1.0);
+        clock.observe(0, 1.0);
+        clock.observe(1, 1.2);
+        assert_eq!(Repeat::Turn.interval(&clock), 1.0);
+
+        assert_eq!("turn".parse(), Ok(Repeat::Turn));
+        assert_eq!("150".parse(), Ok(Repeat::Every(Duration::from_millis(150))));
+        assert!("0".parse::<Repeat>().is_err());
+    }
+}
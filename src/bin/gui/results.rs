@@ -0,0 +1,147 @@
+//! The results of a game, shown after it ends instead of going straight to the lobby.
+
+use std::collections::BTreeMap;
+
+use bevy::input::keyboard::KeyboardInput;
+use bevy::prelude::*;
+
+use robots::{DisplayMessage, Player, PlayerId, Score};
+
+use crate::scene::{player_colors, Scene};
+use crate::{LoadedFont, ARGS};
+
+/// The last state of a game which has ended.
+#[derive(Debug, Clone)]
+pub struct Results {
+    pub game: DisplayMessage,
+    /// When the game ended, in seconds since startup.
+    pub since: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rank {
+    /// Players with as many deaths share a place.
+    pub place: usize,
+    pub id: PlayerId,
+    /// Unknown if the client did not send a score.
+    pub deaths: Option<u32>,
+}
+
+/// Players from the fewest deaths, then by id. Players without a score are last.
+pub fn ranking(
+    players: &BTreeMap<PlayerId, Player>,
+    scores: &BTreeMap<PlayerId, Score>,
+) -> Vec<Rank> {
+    let mut ranks = players
+        .keys()
+        .map(|&id| Rank {
+            place: 0,
+            id,
+            deaths: scores.get(&id).map(|score| score.deaths),
+        })
+        .collect::<Vec<_>>();
+    let key = |rank: &Rank| (rank.deaths.is_none(), rank.deaths);
+    ranks.sort_by_key(|rank| (key(rank), rank.id));
+    for index in 0..ranks.len() {
+        ranks[index].place = match index {
+            0 => 1,
+            _ if key(&ranks[index]) == key(&ranks[index - 1]) => ranks[index - 1].place,
+            _ => index + 1,
+        };
+    }
+    ranks
+}
+
+pub fn results_text(game: &DisplayMessage, loaded_font: &LoadedFont) -> Text {
+    let (server_name, turn, players, scores) = match game {
+        DisplayMessage::Game {
+            server_name,
+            turn,
+            players,
+            scores,
+            ..
+        } => (server_name, turn, players, scores),
+        DisplayMessage::Lobby { .. } => return Text::default(),
+    };
+    let style = |font_size: f32, color: Color| TextStyle {
+        font: loaded_font.0.clone(),
+        font_size,
+        color,
+    };
+    let colors = player_colors(players);
+    let mut sections = vec![TextSection {
+        value: format!("Game over after {turn} turns\n{server_name}\n\n"),
+        style: style(30.0, Color::WHITE),
+    }];
+    for rank in ranking(players, scores) {
+        let deaths = match rank.deaths {
+            Some(1) => "1 death".to_string(),
+            Some(deaths) => format!("{} deaths", deaths),
+            None => "no score".to_string(),
+        };
+        sections.push(TextSection {
+            value: format!(
+                "{}. ({}) {} - {}\n",
+                rank.place, rank.id.0, players[&rank.id].name, deaths
+            ),
+            style: style(22.0, colors[&rank.id]),
+        });
+    }
+    sections.push(TextSection {
+        value: "\nPress any key to see the lobby".to_string(),
+        style: style(15.0, Color::GRAY),
+    });
+    Text {
+        sections,
+        alignment: TextAlignment {
+            vertical: VerticalAlign::Center,
+            horizontal: HorizontalAlign::Center,
+        },
+    }
+}
+
+/// Goes on to the lobby after `--results` seconds or when a key is pressed.
+pub fn expire(time: Res<Time>, mut keys: EventReader<KeyboardInput>, mut scene: ResMut<Scene>) {
+    let pressed = keys.iter().any(|event| event.state.is_pressed());
+    let since = match scene.results() {
+        Some(results) => results.since,
+        None => return,
+    };
+    if pressed || time.seconds_since_startup() - since >= ARGS.results as f64 {
+        scene.dismiss_results();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ranks() {
+        let player = Player {
+            name: "robot".parse().unwrap(),
+            address: "127.0.0.1:2022".parse().unwrap(),
+        };
+        let players = (0..5)
+            .map(|id| (PlayerId(id), player.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let scores = [(0, 3), (1, 1), (3, 1), (4, 0)]
+            .into_iter()
+            .map(|(id, deaths)| (PlayerId(id), Score { deaths }))
+            .collect();
+        let ranks = ranking(&players, &scores)
+            .into_iter()
+            .map(|rank| (rank.place, rank.id.0, rank.deaths))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranks,
+            vec![
+                (1, 4, Some(0)),
+                (2, 1, Some(1)),
+                (2, 3, Some(1)),
+                (4, 0, Some(3)),
+                (5, 2, None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! Click-to-move. A left click on a cell plans a path for the robot of the player driven by
+//! the first profile, and a `Move` is sent for each turn until the robot gets there.
+//! The route is dropped when a block appears on it, when the robot leaves it (e.g. after
+//! being destroyed) or when a key of the first profile is pressed.
+
+use bevy::input::keyboard::KeyboardInput;
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use robots::serialize::serializer;
+use robots::{Board, Direction, DisplayMessage, InputMessage, PlayerId, Position};
+
+use crate::bindings::Bindings;
+use crate::layout::Layout;
+use crate::scene::Scene;
+use crate::view::View;
+use crate::{InputMessageSender, ARGS};
+
+/// Turns the robot may stay in place, e.g. behind another robot, before the route is dropped.
+const MAX_STUCK_TURNS: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Route {
+    /// Cells from the start of the route to the target, both included.
+    cells: Vec<Position>,
+    /// The step from each cell to the next one.
+    directions: Vec<Direction>,
+    /// The turn of the last message handled.
+    turn: u16,
+    stuck: u8,
+}
+
+/// What to do with the route after a new turn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Progress {
+    Step(Direction),
+    Arrived,
+    Cancelled(&'static str),
+}
+
+impl Route {
+    fn plan(from: Position, to: Position, board: &Board, blocks: &[Position]) -> Option<Route> {
+        let directions = from.path_to(to, board, |position| blocks.contains(&position))?;
+        let mut cells = vec![from];
+        for direction in &directions {
+            let last = *cells.last().expect("starts with one cell");
+            cells.push(
+                last.step(*direction, board)
+                    .expect("the path is on the board"),
+            );
+        }
+        Some(Route {
+            cells,
+            directions,
+            turn: 0,
+            stuck: 0,
+        })
+    }
+
+    /// The next step from where the robot is now.
+    fn advance(&mut self, robot: Position, blocks: &[Position]) -> Progress {
+        let index = match self.cells.iter().position(|&cell| cell == robot) {
+            Some(index) => index,
+            None => return Progress::Cancelled("the robot left the route"),
+        };
+        if index == self.cells.len() - 1 {
+            return Progress::Arrived;
+        }
+        if self.cells[index + 1..]
+            .iter()
+            .any(|cell| blocks.contains(cell))
+        {
+            return Progress::Cancelled("the route is blocked");
+        }
+        if index == 0 {
+            self.stuck += 1;
+            if self.stuck > MAX_STUCK_TURNS {
+                return Progress::Cancelled("the robot does not move");
+            }
+        } else {
+            self.cells.drain(..index);
+            self.directions.drain(..index);
+            self.stuck = 0;
+        }
+        Progress::Step(self.directions[0])
+    }
+}
+
+/// The route being followed, if any.
+#[derive(Default)]
+pub struct Router {
+    route: Option<Route>,
+}
+
+/// The robot of the player named by `--player-name`, or else of the followed player.
+fn own_robot(message: &DisplayMessage, view: &View) -> Option<(PlayerId, Position)> {
+    let (players, player_positions) = match message {
+        DisplayMessage::Game {
+            players,
+            player_positions,
+            ..
+        } => (players, player_positions),
+        DisplayMessage::Lobby { .. } => return None,
+    };
+    let id = match &ARGS.player_name {
+        Some(name) => players
+            .iter()
+            .find(|(_, player)| player.name.as_str() == name)
+            .map(|(id, _)| *id),
+        None => view.follow,
+    }?;
+    Some((id, *player_positions.get(&id)?))
+}
+
+/// Blocks and the board of a game message.
+fn obstacles(message: &DisplayMessage) -> Option<(Board, Vec<Position>, u16)> {
+    match message {
+        DisplayMessage::Game {
+            size_x,
+            size_y,
+            blocks,
+            turn,
+            ..
+        } => Some((
+            Board::new(*size_x, *size_y)?,
+            blocks.iter().copied().collect(),
+            *turn,
+        )),
+        DisplayMessage::Lobby { .. } => None,
+    }
+}
+
+fn send(socket: &InputMessageSender, bindings: &Bindings, direction: Direction) {
+    let profile = match bindings.profiles.first() {
+        Some(profile) => profile,
+        None => return,
+    };
+    let message = InputMessage::Move { direction };
+    info!("Route: sending {:?} to {}", message, profile.client_address);
+    if let Err(e) = socket.send_to(&serializer::to_bytes(message), profile.client_address) {
+        error!("{}", e);
+    }
+}
+
+/// The cell under the cursor, if it is over the board.
+fn clicked_cell(cursor: Vec2, layout: &Layout, view: &View) -> Option<Position> {
+    if cursor.cmplt(layout.area_origin).any()
+        || cursor.cmpgt(layout.area_origin + layout.area_size).any()
+    {
+        return None;
+    }
+    let cell = (view.board_point(layout, cursor) - layout.board_origin) / layout.cell;
+    let size = Vec2::new(layout.size_x as f32, layout.size_y as f32);
+    if cell.cmplt(Vec2::ZERO).any() || cell.cmpge(size).any() {
+        return None;
+    }
+    Some(Position(cell.x as u16, cell.y as u16))
+}
+
+/// Plans a route to the clicked cell and takes its first step at once.
+pub fn click(
+    buttons: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    scene: Res<Scene>,
+    view: Res<View>,
+    bindings: Res<Bindings>,
+    socket: Res<InputMessageSender>,
+    mut router: ResMut<Router>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let size = Vec2::new(window.width(), window.height());
+    let (layout, message) = match (scene.layout(), scene.last()) {
+        (Some(layout), Some(message)) => (layout, message),
+        _ => return,
+    };
+    let target = match window
+        .cursor_position()
+        .and_then(|cursor| clicked_cell(cursor - size / 2.0, &layout, &view))
+    {
+        Some(target) => target,
+        None => return,
+    };
+    let robot = own_robot(message, &view);
+    let ((id, robot), (board, blocks, turn)) = match (robot, obstacles(message)) {
+        (Some(robot), Some(obstacles)) => (robot, obstacles),
+        _ => {
+            warn!("Route: no robot to move, use --player-name or follow a player with F");
+            return;
+        }
+    };
+    let mut route = match Route::plan(robot, target, &board, &blocks) {
+        Some(route) => route,
+        None => {
+            warn!("Route: no path to ({}, {})", target.0, target.1);
+            router.route = None;
+            return;
+        }
+    };
+    info!(
+        "Route: player {} to ({}, {}) in {} moves",
+        id.0,
+        target.0,
+        target.1,
+        route.cells.len() - 1
+    );
+    route.turn = turn;
+    match route.advance(robot, &blocks) {
+        Progress::Step(direction) => {
+            send(&socket, &bindings, direction);
+            router.route = Some(route);
+        }
+        _ => router.route = None,
+    }
+}
+
+/// Takes a step for every new turn, and drops the route when a key of the first profile is pressed.
+pub fn follow_route(
+    mut messages: EventReader<DisplayMessage>,
+    mut keys: EventReader<KeyboardInput>,
+    view: Res<View>,
+    bindings: Res<Bindings>,
+    socket: Res<InputMessageSender>,
+    mut router: ResMut<Router>,
+) {
+    let first = bindings.profiles.first().map(|profile| &profile.name);
+    let pressed = keys.iter().any(|event| {
+        event.state.is_pressed()
+            && bindings
+                .lookup(event.key_code, event.scan_code)
+                .is_some_and(|(profile, _)| Some(&profile.name) == first)
+    });
+    if pressed && router.route.take().is_some() {
+        info!("Route: cancelled by a key");
+    }
+    let message = match messages.iter().last() {
+        Some(message) => message,
+        None => return,
+    };
+    let route = match &mut router.route {
+        Some(route) => route,
+        None => return,
+    };
+    let progress = match (obstacles(message), own_robot(message, &view)) {
+        (Some((_, blocks, turn)), Some((_, robot))) => {
+            if turn <= route.turn {
+                return;
+            }
+            route.turn = turn;
+            route.advance(robot, &blocks)
+        }
+        _ => Progress::Cancelled("the robot is gone"),
+    };
+    match progress {
+        Progress::Step(direction) => send(&socket, &bindings, direction),
+        Progress::Arrived => {
+            info!("Route: arrived");
+            router.route = None;
+        }
+        Progress::Cancelled(reason) => {
+            info!("Route: cancelled, {}", reason);
+            router.route = None;
+        }
+    }
+}
+
+/// Marks the cells left on the route.
+#[derive(Component)]
+pub struct RouteMarker;
+
+pub fn draw_route(
+    mut commands: Commands,
+    scene: Res<Scene>,
+    router: Res<Router>,
+    mut shown: Local<Option<(Layout, Vec<Position>)>>,
+    markers: Query<Entity, With<RouteMarker>>,
+) {
+    let wanted = match (scene.layout(), &router.route) {
+        (Some(layout), Some(route)) => Some((layout, route.cells[1..].to_vec())),
+        _ => None,
+    };
+    if *shown == wanted {
+        return;
+    }
+    for entity in markers.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *shown = wanted;
+    let ((layout, cells), root) = match (&*shown, scene.root()) {
+        (Some(wanted), Some(root)) => (wanted, root),
+        _ => return,
+    };
+    for (index, &cell) in cells.iter().enumerate() {
+        let target = index == cells.len() - 1;
+        let shape = shapes::Circle {
+            radius: layout.cell * if target { 0.3 } else { 0.15 },
+            center: Vec2::ZERO,
+        };
+        let marker = commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &shape,
+                DrawMode::Fill(FillMode::color(Color::rgba(1.0, 1.0, 1.0, 0.6))),
+                Transform::from_translation(layout.cell_centre(cell).extend(2.5)),
+            ))
+            .insert(RouteMarker)
+            .id();
+        commands.entity(root).add_child(marker);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn follows_the_route() {
+        let board = Board::new(4, 3).unwrap();
+        let blocks = vec![Position(1, 0), Position(1, 1)];
+        let mut route = Route::plan(Position(0, 0), Position(2, 0), &board, &blocks).unwrap();
+        assert_eq!(route.cells.last(), Some(&Position(2, 0)));
+        assert_eq!(route.cells.len(), 7);
+
+        assert_eq!(
+            route.advance(Position(0, 0), &blocks),
+            Progress::Step(Direction::Up)
+        );
+        // The server did not move the robot in this turn.
+        assert_eq!(
+            route.advance(Position(0, 0), &blocks),
+            Progress::Step(Direction::Up)
+        );
+        assert_eq!(
+            route.advance(Position(0, 1), &blocks),
+            Progress::Step(Direction::Up)
+        );
+        assert_eq!(
+            route.advance(Position(0, 2), &blocks),
+            Progress::Step(Direction::Right)
+        );
+        assert_eq!(route.cells[0], Position(0, 2));
+        // Skipping ahead is fine too.
+        assert_eq!(
+            route.advance(Position(2, 1), &blocks),
+            Progress::Step(Direction::Down)
+        );
+        assert_eq!(route.advance(Position(2, 0), &blocks), Progress::Arrived);
+    }
+
+    #[test]
+    fn cancels() {
+        let board = Board::new(4, 3).unwrap();
+        let mut route = Route::plan(Position(0, 0), Position(3, 0), &board, &[]).unwrap();
+        assert_eq!(
+            route.advance(Position(0, 0), &[]),
+            Progress::Step(Direction::Right)
+        );
+        assert_eq!(
+            route.advance(Position(1, 0), &[Position(2, 0)]),
+            Progress::Cancelled("the route is blocked")
+        );
+        assert_eq!(
+            route.advance(Position(0, 2), &[]),
+            Progress::Cancelled("the robot left the route")
+        );
+
+        let mut route = Route::plan(Position(0, 0), Position(3, 0), &board, &[]).unwrap();
+        for _ in 0..MAX_STUCK_TURNS {
+            assert!(matches!(
+                route.advance(Position(0, 0), &[]),
+                Progress::Step(_)
+            ));
+        }
+        assert_eq!(
+            route.advance(Position(0, 0), &[]),
+            Progress::Cancelled("the robot does not move")
+        );
+    }
+}
@@ -0,0 +1,819 @@
+//! Drawing of `DisplayMessage`s. Entities live as long as what they show:
+//! only the blocks, bombs, explosions and robots which changed between messages
+//! are spawned, moved or despawned, and the grid is built once per board size.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+
+use bevy::prelude::*;
+use bevy::text::Text2dBounds;
+use bevy::window::WindowResized;
+use bevy_prototype_lyon::prelude::*;
+
+use robots::display::{self, Inconsistency};
+use robots::{DisplayMessage, Player, PlayerId, Position};
+
+use crate::layout::{Layout, MARGIN};
+use crate::results::{results_text, Results};
+use crate::{LoadedFont, ARGS};
+
+const COLORS: &[Color] = &[
+    Color::RED,
+    Color::GREEN,
+    Color::BLUE,
+    Color::YELLOW,
+    Color::CYAN,
+    Color::PURPLE,
+    Color::SALMON,
+    Color::ORANGE,
+    Color::PINK,
+    Color::OLIVE,
+];
+
+/// Lines between cells are drawn only if cells are at least this many pixels wide,
+/// otherwise the grid would be a solid black square.
+const MIN_GRID_LINE_SPACING: f32 = 4.0;
+
+/// Bombs whose first explosion is at most this many turns away pulse.
+const PULSE_TIMER: u16 = 1;
+
+/// Pulses per second.
+const PULSE_FREQUENCY: f64 = 3.0;
+
+/// Timers are not written on cells smaller than this many pixels, they would be unreadable.
+const MIN_LABEL_CELL: f32 = 12.0;
+
+/// Texts shown next to the board.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    /// Shown until the first message arrives.
+    Status,
+    /// Server, parameters and players of the game about to start, next to the empty board.
+    Lobby,
+    /// Server, turn and scores, in the column left of the board.
+    Scores,
+    /// Lists what is wrong with the last message, if anything.
+    Diagnostics,
+    /// Ranking of the game which has just ended.
+    Results,
+}
+
+/// Parent of everything drawn on the board, moved and scaled by the view.
+#[derive(Component)]
+pub struct BoardRoot;
+
+#[derive(Component)]
+pub struct Robot;
+
+/// Turns left until the first bomb on the cell explodes.
+#[derive(Component)]
+pub struct Fuse(u16);
+
+/// Timers of the bombs on a cell.
+#[derive(Component)]
+pub struct BombLabel;
+
+#[derive(Clone, Copy)]
+enum SpawnType {
+    Player(Color),
+    /// The lowest timer of the bombs on the cell.
+    Bomb(u16),
+    Block,
+    Explosion,
+}
+
+impl SpawnType {
+    fn z(self) -> f32 {
+        match self {
+            SpawnType::Player(_) => 3.0,
+            SpawnType::Bomb(_) => 2.0,
+            SpawnType::Block => 1.0,
+            SpawnType::Explosion => 4.0,
+        }
+    }
+}
+
+struct Spawner {
+    layout: Layout,
+    root: Entity,
+    grid_shape: shapes::Rectangle,
+    bomb_shape: shapes::Ellipse,
+    player_shape: shapes::Polygon,
+    explosion_shape: shapes::Polygon,
+}
+
+impl Spawner {
+    fn new(layout: Layout, root: Entity) -> Self {
+        let cell = layout.cell;
+        let grid_shape = shapes::Rectangle {
+            extents: Vec2::new(cell, cell),
+            origin: RectangleOrigin::TopLeft,
+        };
+        let bomb_shape = shapes::Ellipse {
+            radii: Vec2::new(cell / 2.0, cell / 2.0),
+            center: Vec2::new(cell / 2.0, -cell / 2.0),
+        };
+        let explosion_shape = shapes::Polygon {
+            points: vec![
+                Vec2::new(0.0, -cell),
+                Vec2::new(cell, 0.0),
+                Vec2::new(cell, -cell),
+                Vec2::new(0.0, 0.0),
+            ],
+            closed: false,
+        };
+
+        let player_shape = shapes::Polygon {
+            points: vec![
+                Vec2::new(0.0, -cell),
+                Vec2::new(cell, -cell),
+                Vec2::new(cell / 2.0, 0.0),
+            ],
+            closed: true,
+        };
+        Self {
+            layout,
+            root,
+            grid_shape,
+            bomb_shape,
+            player_shape,
+            explosion_shape,
+        }
+    }
+
+    fn contains(&self, position: Position) -> bool {
+        position.0 < self.layout.size_x && position.1 < self.layout.size_y
+    }
+
+    /// Where the top-left corner of the cell is.
+    fn translation(&self, position: Position, z: f32) -> Vec3 {
+        self.layout.cell_top_left(position).extend(z)
+    }
+
+    fn draw_mode(what: SpawnType) -> DrawMode {
+        let color = match what {
+            SpawnType::Player(c) => c,
+            SpawnType::Bomb(timer) => fuse_color(timer),
+            SpawnType::Block => Color::DARK_GRAY,
+            SpawnType::Explosion => Color::BLACK,
+        };
+        outlined(color)
+    }
+
+    fn spawn(&self, commands: &mut Commands, position: Position, what: SpawnType) -> Entity {
+        let transform = Transform::from_translation(self.translation(position, what.z()));
+        let draw_mode = Self::draw_mode(what);
+        let bundle = match what {
+            SpawnType::Player(_) => {
+                GeometryBuilder::build_as(&self.player_shape, draw_mode, transform)
+            }
+            SpawnType::Bomb(_) => GeometryBuilder::build_as(&self.bomb_shape, draw_mode, transform),
+            SpawnType::Block => GeometryBuilder::build_as(&self.grid_shape, draw_mode, transform),
+            SpawnType::Explosion => {
+                GeometryBuilder::build_as(&self.explosion_shape, draw_mode, transform)
+            }
+        };
+        let mut entity = commands.spawn_bundle(bundle);
+        match what {
+            SpawnType::Player(_) => {
+                entity.insert(Robot);
+            }
+            SpawnType::Bomb(timer) => {
+                entity.insert(Fuse(timer));
+            }
+            _ => {}
+        }
+        let entity = entity.id();
+        commands.entity(self.root).add_child(entity);
+        entity
+    }
+
+    /// Timers of the bombs on the cell, written over its centre.
+    fn spawn_label(
+        &self,
+        commands: &mut Commands,
+        position: Position,
+        timers: &[u16],
+        font: &Handle<Font>,
+    ) -> Entity {
+        let cell = self.layout.cell;
+        let center = Vec3::new(cell / 2.0, -cell / 2.0, 0.0);
+        let label = commands
+            .spawn_bundle(Text2dBundle {
+                text: self.label_text(timers, font),
+                transform: Transform::from_translation(self.translation(position, 5.0) + center),
+                visibility: Visibility {
+                    is_visible: cell >= MIN_LABEL_CELL,
+                },
+                ..default()
+            })
+            .insert(BombLabel)
+            .id();
+        commands.entity(self.root).add_child(label);
+        label
+    }
+
+    /// Stacked bombs are listed from the one which explodes first, e.g. `1/3`.
+    fn label_text(&self, timers: &[u16], font: &Handle<Font>) -> Text {
+        let value = timers
+            .iter()
+            .map(|timer| timer.to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        let cell = self.layout.cell;
+        let font_size = (cell * 0.6).min(cell * 1.2 / value.len() as f32);
+        Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            TextAlignment {
+                vertical: VerticalAlign::Center,
+                horizontal: HorizontalAlign::Center,
+            },
+        )
+    }
+
+    /// The grid is a single background rectangle with the lines between cells on top of it,
+    /// instead of an entity per cell.
+    fn spawn_grid(&self, commands: &mut Commands) -> Vec<Entity> {
+        let (cell, size) = (self.layout.cell, self.layout.board_size());
+        let top_left = self.translation(Position(0, self.layout.size_y - 1), 0.0);
+        let background = shapes::Rectangle {
+            extents: size,
+            origin: RectangleOrigin::TopLeft,
+        };
+        let mut entities = vec![commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &background,
+                outlined(Color::WHITE),
+                Transform::from_translation(top_left),
+            ))
+            .id()];
+
+        if cell >= MIN_GRID_LINE_SPACING {
+            let mut lines = GeometryBuilder::new();
+            for x in 1..self.layout.size_x {
+                let x = x as f32 * cell;
+                lines = lines.add(&shapes::Line(Vec2::new(x, 0.0), Vec2::new(x, -size.y)));
+            }
+            for y in 1..self.layout.size_y {
+                let y = -(y as f32) * cell;
+                lines = lines.add(&shapes::Line(Vec2::new(0.0, y), Vec2::new(size.x, y)));
+            }
+            let transform = Transform::from_translation(top_left + Vec3::new(0.0, 0.0, 0.5));
+            let draw_mode = DrawMode::Stroke(StrokeMode::new(Color::BLACK, 1.0));
+            entities.push(
+                commands
+                    .spawn_bundle(lines.build(draw_mode, transform))
+                    .id(),
+            );
+        }
+        commands.entity(self.root).push_children(&entities);
+        entities
+    }
+}
+
+fn outlined(color: Color) -> DrawMode {
+    DrawMode::Outlined {
+        fill_mode: FillMode::color(color),
+        outline_mode: StrokeMode::new(Color::BLACK, 1.0),
+    }
+}
+
+/// Colours of the known players, in the order of their ids.
+pub fn player_colors(players: &BTreeMap<PlayerId, Player>) -> HashMap<PlayerId, Color> {
+    players
+        .keys()
+        .zip(COLORS.iter().cycle())
+        .map(|(id, &color)| (*id, color))
+        .collect()
+}
+
+/// Darker for bombs far from exploding, bright red for the ones exploding in the next turn.
+fn fuse_color(timer: u16) -> Color {
+    let urgency = 1.0 / timer.max(1) as f32;
+    Color::rgb(0.3 + 0.7 * urgency, 0.0, 0.0)
+}
+
+/// Bombs sharing a cell.
+struct BombStack {
+    shape: Entity,
+    label: Entity,
+    /// In ascending order.
+    timers: Vec<u16>,
+}
+
+/// Entities showing the current game, by what they show.
+#[derive(Default)]
+pub struct Scene {
+    root: Option<Entity>,
+    spawner: Option<Spawner>,
+    grid: Vec<Entity>,
+    blocks: HashMap<Position, Entity>,
+    explosions: HashMap<Position, Entity>,
+    bombs: HashMap<Position, BombStack>,
+    robots: HashMap<PlayerId, (Entity, Color)>,
+    /// Drawn again when the window is resized.
+    last: Option<DisplayMessage>,
+    /// Shown instead of the lobby for a while after a game.
+    results: Option<Results>,
+    /// Draw `last` again even without a new message.
+    redraw: bool,
+}
+
+impl Scene {
+    /// The layout of the board being shown, if any.
+    pub fn layout(&self) -> Option<Layout> {
+        self.spawner.as_ref().map(|spawner| spawner.layout)
+    }
+
+    /// Parent of the entities on the board.
+    pub fn root(&self) -> Option<Entity> {
+        self.root
+    }
+
+    pub fn last(&self) -> Option<&DisplayMessage> {
+        self.last.as_ref()
+    }
+
+    pub fn results(&self) -> Option<&Results> {
+        self.results.as_ref()
+    }
+
+    /// Goes on to the lobby.
+    pub fn dismiss_results(&mut self) {
+        self.results = None;
+        self.redraw = true;
+    }
+
+    /// Despawns everything, e.g. when the game ends.
+    fn clear(&mut self, commands: &mut Commands) {
+        self.clear_pieces(commands);
+        for entity in self.grid.drain(..) {
+            commands.entity(entity).despawn_recursive();
+        }
+        self.spawner = None;
+    }
+
+    /// Despawns everything but the grid, e.g. when going back to the lobby.
+    fn clear_pieces(&mut self, commands: &mut Commands) {
+        let entities = self
+            .blocks
+            .drain()
+            .map(|(_, entity)| entity)
+            .chain(self.explosions.drain().map(|(_, entity)| entity))
+            .chain(
+                self.bombs
+                    .drain()
+                    .flat_map(|(_, stack)| [stack.shape, stack.label]),
+            )
+            .chain(self.robots.drain().map(|(_, (entity, _))| entity));
+        for entity in entities {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Keeps exactly one entity per wanted key: despawns the ones whose key is gone
+/// and spawns the missing ones. The rest are left untouched.
+fn sync<K: Copy + Eq + Hash>(
+    commands: &mut Commands,
+    entities: &mut HashMap<K, Entity>,
+    wanted: impl IntoIterator<Item = K>,
+    mut spawn: impl FnMut(&mut Commands, K) -> Entity,
+) {
+    let wanted = wanted.into_iter().collect::<HashSet<_>>();
+    entities.retain(|key, entity| {
+        let keep = wanted.contains(key);
+        if !keep {
+            commands.entity(*entity).despawn_recursive();
+        }
+        keep
+    });
+    for key in wanted {
+        entities.entry(key).or_insert_with(|| spawn(commands, key));
+    }
+}
+
+pub fn setup(mut commands: Commands, mut scene: ResMut<Scene>) {
+    scene.root = Some(
+        commands
+            .spawn_bundle(TransformBundle::default())
+            .insert(BoardRoot)
+            .id(),
+    );
+    for panel in [
+        Panel::Lobby,
+        Panel::Scores,
+        Panel::Diagnostics,
+        Panel::Results,
+    ] {
+        commands
+            .spawn_bundle(Text2dBundle {
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(panel);
+    }
+}
+
+/// Places the panel in the layout, wrapping its text to the space it gets.
+fn place(panel: Panel, layout: &Layout) -> Option<(Vec3, Text2dBounds)> {
+    let bounds = |width: f32, height: f32| Text2dBounds {
+        size: Size::new(width, height),
+    };
+    let panel_width = layout.panel_size.x;
+    match panel {
+        Panel::Status => None,
+        Panel::Lobby | Panel::Scores => Some((
+            layout.panel_origin.extend(9.0),
+            bounds(panel_width, layout.panel_size.y),
+        )),
+        Panel::Diagnostics => Some((
+            layout.panel_bottom_left().extend(10.0),
+            bounds(panel_width, layout.panel_size.y / 2.0),
+        )),
+        Panel::Results => Some((
+            Vec3::new(0.0, 0.0, 9.0),
+            bounds(layout.window.x - 2.0 * MARGIN, layout.window.y),
+        )),
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn draw(
+    mut commands: Commands,
+    mut reader: EventReader<DisplayMessage>,
+    mut resized: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    mut scene: ResMut<Scene>,
+    mut panels: Query<
+        (
+            &Panel,
+            &mut Text,
+            &mut Visibility,
+            &mut Transform,
+            &mut Text2dBounds,
+        ),
+        (Without<BombLabel>, Without<Robot>),
+    >,
+    mut robots: Query<(&mut Transform, &mut DrawMode), With<Robot>>,
+    mut fuses: Query<(&mut Fuse, &mut DrawMode), Without<Robot>>,
+    mut labels: Query<&mut Text, (With<BombLabel>, Without<Panel>)>,
+    loaded_font: Res<LoadedFont>,
+    time: Res<Time>,
+) {
+    // A resize redraws the last message in the new layout.
+    let resized = resized.iter().count() > 0;
+    match reader.iter().last() {
+        Some(event) => {
+            info!("{}", serde_json::to_string(&event).unwrap());
+            for inconsistency in display::inconsistencies(event) {
+                warn!("Inconsistent message: {}", inconsistency);
+            }
+            match (scene.last.take(), event) {
+                // The client goes back to the lobby as soon as the game ends,
+                // so its last state is kept for the results.
+                (Some(game @ DisplayMessage::Game { .. }), DisplayMessage::Lobby { .. })
+                    if ARGS.results > 0 =>
+                {
+                    scene.results = Some(Results {
+                        game,
+                        since: time.seconds_since_startup(),
+                    });
+                }
+                (_, DisplayMessage::Game { .. }) => scene.results = None,
+                _ => {}
+            }
+            scene.last = Some(event.clone());
+        }
+        None if resized || scene.redraw => {}
+        None => return,
+    }
+    scene.redraw = false;
+    let results = scene.results.as_ref().map(|results| results.game.clone());
+    let (event, window) = match (&scene.last, windows.get_primary()) {
+        (Some(event), Some(window)) => (event.clone(), Vec2::new(window.width(), window.height())),
+        _ => return,
+    };
+    let event = &event;
+    let inconsistencies = display::inconsistencies(event);
+    let layout = match event {
+        DisplayMessage::Lobby { size_x, size_y, .. }
+        | DisplayMessage::Game { size_x, size_y, .. } => Layout::new(window, *size_x, *size_y),
+    };
+
+    for (panel, mut text, mut visibility, mut transform, mut bounds) in panels.iter_mut() {
+        let shown = match (panel, event) {
+            (Panel::Results, _) => results
+                .as_ref()
+                .map(|game| results_text(game, &loaded_font)),
+            (Panel::Lobby, DisplayMessage::Lobby { .. }) if results.is_none() => {
+                Some(lobby_text(event, &loaded_font))
+            }
+            (Panel::Scores, DisplayMessage::Game { .. }) => Some(scores_text(event, &loaded_font)),
+            (Panel::Diagnostics, _) if !inconsistencies.is_empty() => {
+                Some(diagnostics_text(&inconsistencies, &loaded_font))
+            }
+            _ => None,
+        };
+        visibility.is_visible = shown.is_some();
+        if let Some(shown) = shown {
+            *text = shown;
+        }
+        if let Some((translation, place_bounds)) = place(*panel, &layout) {
+            transform.translation = translation;
+            *bounds = place_bounds;
+        }
+    }
+
+    // The results cover the whole window, and a board without cells cannot be drawn.
+    if results.is_some() || layout.size_x == 0 || layout.size_y == 0 {
+        scene.clear(&mut commands);
+        return;
+    }
+    // Everything is spawned again in a new layout, the shapes depend on the cell size.
+    // The grid stays from the lobby, where it previews the board, to the game.
+    if scene.spawner.as_ref().map(|spawner| spawner.layout) != Some(layout) {
+        scene.clear(&mut commands);
+        let root = scene.root.expect("spawned in setup");
+        let spawner = Spawner::new(layout, root);
+        scene.grid = spawner.spawn_grid(&mut commands);
+        scene.spawner = Some(spawner);
+    }
+
+    match event {
+        DisplayMessage::Lobby { .. } => scene.clear_pieces(&mut commands),
+        DisplayMessage::Game {
+            players,
+            player_positions,
+            blocks,
+            bombs,
+            explosions,
+            ..
+        } => {
+            let scene = &mut *scene;
+            let spawner = scene.spawner.as_ref().expect("board was just set up");
+
+            sync(
+                &mut commands,
+                &mut scene.blocks,
+                blocks.iter().copied().filter(|&p| spawner.contains(p)),
+                |commands, position| spawner.spawn(commands, position, SpawnType::Block),
+            );
+            sync(
+                &mut commands,
+                &mut scene.explosions,
+                explosions.iter().copied().filter(|&p| spawner.contains(p)),
+                |commands, position| spawner.spawn(commands, position, SpawnType::Explosion),
+            );
+
+            let mut stacks = HashMap::<Position, Vec<u16>>::new();
+            for bomb in bombs.iter().filter(|bomb| spawner.contains(bomb.position)) {
+                stacks.entry(bomb.position).or_default().push(bomb.timer);
+            }
+            scene.bombs.retain(|position, stack| {
+                let keep = stacks.contains_key(position);
+                if !keep {
+                    commands.entity(stack.shape).despawn_recursive();
+                    commands.entity(stack.label).despawn_recursive();
+                }
+                keep
+            });
+            for (position, mut timers) in stacks {
+                timers.sort_unstable();
+                match scene.bombs.get_mut(&position) {
+                    Some(stack) if stack.timers == timers => {}
+                    Some(stack) => {
+                        if let Ok((mut fuse, mut draw_mode)) = fuses.get_mut(stack.shape) {
+                            *fuse = Fuse(timers[0]);
+                            *draw_mode = Spawner::draw_mode(SpawnType::Bomb(timers[0]));
+                        }
+                        if let Ok(mut text) = labels.get_mut(stack.label) {
+                            *text = spawner.label_text(&timers, &loaded_font);
+                        }
+                        stack.timers = timers;
+                    }
+                    None => {
+                        let shape =
+                            spawner.spawn(&mut commands, position, SpawnType::Bomb(timers[0]));
+                        let label =
+                            spawner.spawn_label(&mut commands, position, &timers, &loaded_font);
+                        scene.bombs.insert(
+                            position,
+                            BombStack {
+                                shape,
+                                label,
+                                timers,
+                            },
+                        );
+                    }
+                }
+            }
+
+            // Robots of unknown players are drawn too, in grey. Anything off the board is
+            // not drawn at all, the diagnostics panel lists it instead.
+            let colors = player_colors(players);
+            let shown = player_positions
+                .iter()
+                .filter(|(_, &position)| spawner.contains(position))
+                .map(|(id, &position)| {
+                    let color = colors.get(id).copied().unwrap_or(Color::GRAY);
+                    (*id, position, color)
+                })
+                .collect::<Vec<_>>();
+            scene.robots.retain(|id, (entity, _)| {
+                let keep = shown.iter().any(|(shown, _, _)| shown == id);
+                if !keep {
+                    commands.entity(*entity).despawn_recursive();
+                }
+                keep
+            });
+            for (id, position, color) in shown {
+                let what = SpawnType::Player(color);
+                match scene.robots.get_mut(&id) {
+                    Some((entity, shown_color)) => {
+                        if let Ok((mut transform, mut draw_mode)) = robots.get_mut(*entity) {
+                            let translation = spawner.translation(position, what.z());
+                            if transform.translation != translation {
+                                transform.translation = translation;
+                            }
+                            if *shown_color != color {
+                                *draw_mode = Spawner::draw_mode(what);
+                                *shown_color = color;
+                            }
+                        }
+                    }
+                    None => {
+                        let entity = spawner.spawn(&mut commands, position, what);
+                        scene.robots.insert(id, (entity, color));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn text_style(loaded_font: &LoadedFont) -> TextStyle {
+    TextStyle {
+        font: loaded_font.0.clone(),
+        font_size: 20.0,
+        color: Color::WHITE,
+    }
+}
+
+const TEXT_ALIGNMENT: TextAlignment = TextAlignment {
+    vertical: VerticalAlign::Top,
+    horizontal: HorizontalAlign::Left,
+};
+
+/// Server, progress of joining, parameters of the game and a line per player in their colour.
+fn lobby_text(event: &DisplayMessage, loaded_font: &LoadedFont) -> Text {
+    let sections = match event {
+        DisplayMessage::Lobby {
+            server_name,
+            players_count,
+            size_x,
+            size_y,
+            game_length,
+            explosion_radius,
+            bomb_timer,
+            players,
+        } => {
+            let section = |value: String, font_size: f32, color: Color| TextSection {
+                value,
+                style: TextStyle {
+                    font: loaded_font.0.clone(),
+                    font_size,
+                    color,
+                },
+            };
+            let joined = players.len();
+            let progress = if joined >= *players_count as usize {
+                Color::GREEN
+            } else {
+                Color::YELLOW
+            };
+            let mut sections = vec![
+                section(format!("{server_name}\n"), 20.0, Color::WHITE),
+                section(
+                    format!("{joined} of {players_count} players joined\n\n"),
+                    17.0,
+                    progress,
+                ),
+                section(
+                    format!(
+                        "Board: {size_x} x {size_y}\nGame length: {game_length} turns\n\
+                        Explosion radius: {explosion_radius}\nBomb timer: {bomb_timer} turns\n\n"
+                    ),
+                    15.0,
+                    Color::rgb(0.8, 0.8, 0.8),
+                ),
+                section("Players\n".to_string(), 17.0, Color::WHITE),
+            ];
+            if players.is_empty() {
+                sections.push(section("No one yet\n".to_string(), 15.0, Color::GRAY));
+            }
+            let colors = player_colors(players);
+            for (id, player) in players {
+                sections.push(section(
+                    format!("({}) {}\n", id.0, player.name),
+                    15.0,
+                    colors[id],
+                ));
+                sections.push(section(
+                    format!("      {}\n", player.address),
+                    12.0,
+                    Color::GRAY,
+                ));
+            }
+            sections
+        }
+        DisplayMessage::Game { .. } => Vec::new(),
+    };
+    Text {
+        sections,
+        alignment: TEXT_ALIGNMENT,
+    }
+}
+
+/// The server and the turn, then a line per player in their colour.
+fn scores_text(event: &DisplayMessage, loaded_font: &LoadedFont) -> Text {
+    let sections = match event {
+        DisplayMessage::Game {
+            server_name,
+            game_length,
+            turn,
+            players,
+            player_positions,
+            scores,
+            ..
+        } => std::iter::once(TextSection {
+            value: format!("{server_name}\nTurn {turn}/{game_length}\n\n"),
+            style: text_style(loaded_font),
+        })
+        .chain(
+            players
+                .iter()
+                .zip(COLORS.iter().cycle())
+                .map(|((id, player), &color)| {
+                    // Missing data is shown as such instead of hiding the player.
+                    let score = scores
+                        .get(id)
+                        .map_or_else(|| "?".to_string(), |score| score.deaths.to_string());
+                    let note = if player_positions.contains_key(id) {
+                        ""
+                    } else {
+                        " (no position)"
+                    };
+                    TextSection {
+                        value: format!("({}) {}: {}{}\n", id.0, player.name, score, note),
+                        style: TextStyle {
+                            font: loaded_font.0.clone(),
+                            font_size: 15.0,
+                            color,
+                        },
+                    }
+                }),
+        )
+        .collect(),
+        DisplayMessage::Lobby { .. } => Vec::new(),
+    };
+    Text {
+        sections,
+        alignment: TEXT_ALIGNMENT,
+    }
+}
+
+fn diagnostics_text(inconsistencies: &[Inconsistency], loaded_font: &LoadedFont) -> Text {
+    let lines = inconsistencies
+        .iter()
+        .map(|inconsistency| format!("{}\n", inconsistency))
+        .collect::<String>();
+    Text::with_section(
+        format!("Inconsistent message from the client:\n{}", lines),
+        TextStyle {
+            font: loaded_font.0.clone(),
+            font_size: 13.0,
+            color: Color::ORANGE_RED,
+        },
+        TextAlignment {
+            vertical: VerticalAlign::Bottom,
+            horizontal: HorizontalAlign::Left,
+        },
+    )
+}
+
+/// Makes the bombs which are about to explode flash between red and yellow.
+pub fn pulse_bombs(time: Res<Time>, mut fuses: Query<(&Fuse, &mut DrawMode)>) {
+    let phase = (time.seconds_since_startup() * PULSE_FREQUENCY * std::f64::consts::TAU).sin();
+    let t = (phase as f32 + 1.0) / 2.0;
+    for (fuse, mut draw_mode) in fuses.iter_mut() {
+        if fuse.0 <= PULSE_TIMER {
+            *draw_mode = outlined(Color::rgb(1.0, t * 0.8, 0.0));
+        }
+    }
+}
@@ -0,0 +1,432 @@
+//! Zooming, panning and following a player. The view scales and moves the `BoardRoot`,
+//! the panels stay where the layout put them. Rulers along the left and bottom edges number
+//! the cells in view from the lower-left corner, like positions in the protocol.
+
+use std::ops::Range;
+
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use robots::{DisplayMessage, PlayerId, Position};
+
+use crate::layout::{Layout, RULER};
+use crate::scene::{BoardRoot, Scene};
+use crate::LoadedFont;
+
+/// The most zoomed-in view still shows this many cells across the shorter side of the board area.
+const MIN_VISIBLE_CELLS: f32 = 3.0;
+
+/// Zoom factor of a key press or a notch of the mouse wheel.
+const ZOOM_STEP: f32 = 1.25;
+
+/// Panning with the keyboard, in pixels per second.
+const PAN_SPEED: f32 = 600.0;
+
+/// Ruler labels are at least this many pixels apart.
+const RULER_SPACING: f32 = 32.0;
+
+/// Above everything on the board, below the panels.
+const OVERLAY_Z: f32 = 7.0;
+
+/// Scale and offset of the board relative to the layout, which fits the whole board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    pub scale: f32,
+    /// Offset of the centre of the board area, in pixels.
+    pub pan: Vec2,
+    /// The player whose robot is kept in the centre.
+    pub follow: Option<PlayerId>,
+    /// Size of the board the view was set for, a new board starts fitted again.
+    board: Option<(u16, u16)>,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        View {
+            scale: 1.0,
+            pan: Vec2::ZERO,
+            follow: None,
+            board: None,
+        }
+    }
+}
+
+impl View {
+    fn max_scale(layout: &Layout) -> f32 {
+        (layout.area_size.min_element() / (MIN_VISIBLE_CELLS * layout.cell)).max(1.0)
+    }
+
+    /// Where a point of the fitted board is shown.
+    pub fn screen_point(&self, layout: &Layout, point: Vec2) -> Vec2 {
+        let centre = layout.area_centre();
+        centre + (point - centre) * self.scale + self.pan
+    }
+
+    /// The point of the fitted board shown at `point`.
+    pub fn board_point(&self, layout: &Layout, point: Vec2) -> Vec2 {
+        let centre = layout.area_centre();
+        centre + (point - centre - self.pan) / self.scale
+    }
+
+    pub fn transform(&self, layout: &Layout) -> Transform {
+        let translation = layout.area_centre() * (1.0 - self.scale) + self.pan;
+        Transform {
+            translation: translation.extend(0.0),
+            scale: Vec3::new(self.scale, self.scale, 1.0),
+            ..default()
+        }
+    }
+
+    /// Zooms by `factor`, keeping what is shown at `point` in place.
+    pub fn zoom_at(&mut self, layout: &Layout, point: Vec2, factor: f32) {
+        let scale = (self.scale * factor).clamp(1.0, Self::max_scale(layout));
+        let k = scale / self.scale;
+        self.pan = (point - layout.area_centre()) * (1.0 - k) + self.pan * k;
+        self.scale = scale;
+        self.clamp(layout);
+    }
+
+    pub fn pan_by(&mut self, layout: &Layout, delta: Vec2) {
+        self.pan += delta;
+        self.clamp(layout);
+    }
+
+    /// Moves the cell to the centre of the board area, as far as the edges of the board allow.
+    pub fn centre_on(&mut self, layout: &Layout, position: Position) {
+        self.pan = (layout.area_centre() - layout.cell_centre(position)) * self.scale;
+        self.clamp(layout);
+    }
+
+    /// Keeps the board covering the area in the directions it is larger than it,
+    /// and centred in the others.
+    fn clamp(&mut self, layout: &Layout) {
+        let slack = ((layout.board_size() * self.scale - layout.area_size) / 2.0).max(Vec2::ZERO);
+        self.pan = self.pan.clamp(-slack, slack);
+    }
+
+    /// Columns and rows at least partly in view.
+    pub fn visible_cells(&self, layout: &Layout) -> (Range<u16>, Range<u16>) {
+        let low = self.board_point(layout, layout.area_origin) - layout.board_origin;
+        let high =
+            self.board_point(layout, layout.area_origin + layout.area_size) - layout.board_origin;
+        let range = |low: f32, high: f32, size: u16| {
+            let cell = |value: f32| (value / layout.cell).clamp(0.0, size as f32);
+            cell(low).floor() as u16..cell(high).ceil() as u16
+        };
+        (
+            range(low.x, high.x, layout.size_x),
+            range(low.y, high.y, layout.size_y),
+        )
+    }
+}
+
+/// Every how many cells the rulers are labelled: 1, 2, 5, 10, 20, 50...
+fn ruler_stride(cell: f32) -> u16 {
+    let mut power = 1;
+    loop {
+        for step in [1, 2, 5] {
+            let stride = power * step;
+            if stride as f32 * cell >= RULER_SPACING || stride >= 10_000 {
+                return stride;
+            }
+        }
+        power *= 10;
+    }
+}
+
+/// The next player to follow after `current`, in the order of ids, then nobody.
+fn next_followed(message: Option<&DisplayMessage>, current: Option<PlayerId>) -> Option<PlayerId> {
+    let players = match message {
+        Some(DisplayMessage::Game { players, .. }) => players,
+        _ => return None,
+    };
+    match current {
+        None => players.keys().next().copied(),
+        Some(current) => players.keys().find(|&&id| id > current).copied(),
+    }
+}
+
+fn followed_position(message: Option<&DisplayMessage>, id: PlayerId) -> Option<Position> {
+    match message {
+        Some(DisplayMessage::Game {
+            player_positions, ..
+        }) => player_positions.get(&id).copied(),
+        _ => None,
+    }
+}
+
+/// Zoom with the mouse wheel, `+` and `-`, pan by dragging with the right or middle button
+/// or with Shift and the movement keys, `0` or Home fits the board, `F` picks the player to follow.
+#[allow(clippy::too_many_arguments)]
+pub fn control(
+    mut view: ResMut<View>,
+    scene: Res<Scene>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    mut wheel: EventReader<MouseWheel>,
+    windows: Res<Windows>,
+    time: Res<Time>,
+    mut last_cursor: Local<Option<Vec2>>,
+    mut roots: Query<&mut Transform, With<BoardRoot>>,
+) {
+    let layout = match scene.layout() {
+        Some(layout) => layout,
+        None => {
+            *view = View::default();
+            return;
+        }
+    };
+    let board = Some((layout.size_x, layout.size_y));
+    if view.board != board {
+        *view = View { board, ..default() };
+    }
+    let window = windows.get_primary();
+    let cursor = window.and_then(|window| {
+        let size = Vec2::new(window.width(), window.height());
+        window.cursor_position().map(|cursor| cursor - size / 2.0)
+    });
+    let over_board = cursor.filter(|&cursor| {
+        cursor.cmpge(layout.area_origin).all()
+            && cursor.cmple(layout.area_origin + layout.area_size).all()
+    });
+    let zoom_centre = over_board.unwrap_or_else(|| layout.area_centre());
+
+    for event in wheel.iter() {
+        let notches = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 100.0,
+        };
+        view.zoom_at(&layout, zoom_centre, ZOOM_STEP.powf(notches));
+    }
+    if keys.any_just_pressed([KeyCode::Equals, KeyCode::NumpadAdd]) {
+        view.zoom_at(&layout, layout.area_centre(), ZOOM_STEP);
+    }
+    if keys.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        view.zoom_at(&layout, layout.area_centre(), 1.0 / ZOOM_STEP);
+    }
+    if keys.any_just_pressed([KeyCode::Key0, KeyCode::Numpad0, KeyCode::Home]) {
+        *view = View { board, ..default() };
+    }
+    if keys.just_pressed(KeyCode::F) {
+        view.follow = next_followed(scene.last(), view.follow);
+        info!("Following {:?}", view.follow);
+    }
+
+    let mut pan = Vec2::ZERO;
+    if keys.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+        for (key, direction) in [
+            ([KeyCode::W, KeyCode::Up], Vec2::Y),
+            ([KeyCode::S, KeyCode::Down], -Vec2::Y),
+            ([KeyCode::A, KeyCode::Left], -Vec2::X),
+            ([KeyCode::D, KeyCode::Right], Vec2::X),
+        ] {
+            if keys.any_pressed(key) {
+                // The view moves, so the board moves the other way.
+                pan -= direction * PAN_SPEED * time.delta_seconds();
+            }
+        }
+    }
+    let dragging = buttons.any_pressed([MouseButton::Right, MouseButton::Middle]);
+    if let (true, Some(cursor), Some(last)) = (dragging, cursor, *last_cursor) {
+        pan += cursor - last;
+    }
+    *last_cursor = cursor.filter(|_| dragging);
+    if pan != Vec2::ZERO {
+        view.follow = None;
+        view.pan_by(&layout, pan);
+    }
+
+    if let Some(id) = view.follow {
+        match followed_position(scene.last(), id) {
+            Some(position) => view.centre_on(&layout, position),
+            None => view.follow = None,
+        }
+    }
+    // Resizing the window changes the layout, which may leave the board off-centre.
+    view.clamp(&layout);
+
+    let transform = view.transform(&layout);
+    for mut root in roots.iter_mut() {
+        if *root != transform {
+            *root = transform;
+        }
+    }
+}
+
+/// Frame, rulers and zoom label, all spawned again when the view or the layout changes.
+#[derive(Component)]
+pub struct Overlay;
+
+pub fn draw_overlay(
+    mut commands: Commands,
+    view: Res<View>,
+    scene: Res<Scene>,
+    clear_color: Res<ClearColor>,
+    loaded_font: Res<LoadedFont>,
+    mut shown: Local<Option<(Layout, View)>>,
+    overlay: Query<Entity, With<Overlay>>,
+) {
+    let wanted = scene.layout().map(|layout| (layout, *view));
+    if *shown == wanted {
+        return;
+    }
+    *shown = wanted;
+    for entity in overlay.iter() {
+        commands.entity(entity).despawn();
+    }
+    let (layout, view) = match wanted {
+        Some(wanted) => wanted,
+        None => return,
+    };
+
+    // A zoomed board would cover the panels and the rulers, so everything around
+    // the board area is painted over in the background colour.
+    let half = layout.window / 2.0;
+    let (low, high) = (layout.area_origin, layout.area_origin + layout.area_size);
+    for (origin, extents) in [
+        (-half, Vec2::new(low.x + half.x, layout.window.y)),
+        (
+            Vec2::new(high.x, -half.y),
+            Vec2::new(half.x - high.x, layout.window.y),
+        ),
+        (
+            Vec2::new(low.x, -half.y),
+            Vec2::new(layout.area_size.x, low.y + half.y),
+        ),
+        (
+            Vec2::new(low.x, high.y),
+            Vec2::new(layout.area_size.x, half.y - high.y),
+        ),
+    ] {
+        let shape = shapes::Rectangle {
+            extents,
+            origin: RectangleOrigin::BottomLeft,
+        };
+        commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &shape,
+                DrawMode::Fill(FillMode::color(clear_color.0)),
+                Transform::from_translation(origin.extend(OVERLAY_Z)),
+            ))
+            .insert(Overlay);
+    }
+
+    let style = TextStyle {
+        font: loaded_font.0.clone(),
+        font_size: 11.0,
+        color: Color::WHITE,
+    };
+    let mut label = |text: String, at: Vec2, horizontal: HorizontalAlign| {
+        commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    text,
+                    style.clone(),
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal,
+                    },
+                ),
+                transform: Transform::from_translation(at.extend(OVERLAY_Z + 1.0)),
+                ..default()
+            })
+            .insert(Overlay);
+    };
+    // Next to the visible part of the board, which may be smaller than the area.
+    let edge = view.screen_point(&layout, layout.board_origin).max(low);
+    let stride = ruler_stride(layout.cell * view.scale);
+    let (columns, rows) = view.visible_cells(&layout);
+    for x in columns.filter(|x| x % stride == 0) {
+        let centre = view.screen_point(&layout, layout.cell_centre(Position(x, 0)));
+        if (low.x..=high.x).contains(&centre.x) {
+            let at = Vec2::new(centre.x, edge.y - RULER / 2.0);
+            label(x.to_string(), at, HorizontalAlign::Center);
+        }
+    }
+    for y in rows.filter(|y| y % stride == 0) {
+        let centre = view.screen_point(&layout, layout.cell_centre(Position(0, y)));
+        if (low.y..=high.y).contains(&centre.y) {
+            let at = Vec2::new(edge.x - 3.0, centre.y);
+            label(y.to_string(), at, HorizontalAlign::Right);
+        }
+    }
+
+    let mut status = Vec::new();
+    if view.scale > 1.0 {
+        status.push(format!("x{:.1}", view.scale));
+    }
+    if let Some(id) = view.follow {
+        status.push(format!("following {}", id.0));
+    }
+    if !status.is_empty() {
+        let at = Vec2::new(high.x, high.y + RULER / 2.0);
+        label(status.join(", "), at, HorizontalAlign::Right);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn layout() -> Layout {
+        Layout::new(Vec2::new(818.0, 538.0), 40, 20)
+    }
+
+    #[test]
+    fn zoom_keeps_the_point_under_the_cursor() {
+        let layout = layout();
+        let mut view = View::default();
+        let cursor = layout.area_origin + layout.area_size * Vec2::new(0.3, 0.5);
+        let before = view.board_point(&layout, cursor);
+        view.zoom_at(&layout, cursor, 2.0);
+        assert_eq!(view.scale, 2.0);
+        assert!((view.board_point(&layout, cursor) - before).length() < 1e-3);
+
+        view.zoom_at(&layout, cursor, 0.1);
+        assert_eq!(view, View::default());
+    }
+
+    #[test]
+    fn pan_stops_at_the_edges() {
+        let layout = layout();
+        let mut view = View::default();
+        // The whole board is in view, so there is nothing to pan.
+        view.pan_by(&layout, Vec2::new(50.0, 50.0));
+        assert_eq!(view.pan, Vec2::ZERO);
+
+        view.zoom_at(&layout, layout.area_centre(), 4.0);
+        view.pan_by(&layout, Vec2::new(1e6, 0.0));
+        let (columns, _) = view.visible_cells(&layout);
+        assert_eq!(columns.start, 0);
+        assert_eq!(
+            view.screen_point(&layout, layout.board_origin).x,
+            layout.area_origin.x
+        );
+    }
+
+    #[test]
+    fn follows_a_robot() {
+        let layout = layout();
+        let mut view = View::default();
+        view.zoom_at(&layout, layout.area_centre(), 4.0);
+        view.centre_on(&layout, Position(20, 10));
+        let shown = view.screen_point(&layout, layout.cell_centre(Position(20, 10)));
+        assert!((shown - layout.area_centre()).length() < 1e-3);
+        assert_eq!(view.visible_cells(&layout), (15..26, 5..16));
+
+        // A robot in the corner stays in the corner of the view.
+        view.centre_on(&layout, Position(0, 0));
+        assert_eq!(view.visible_cells(&layout), (0..10, 0..10));
+    }
+
+    #[test]
+    fn ruler_strides() {
+        assert_eq!(ruler_stride(40.0), 1);
+        assert_eq!(ruler_stride(20.0), 2);
+        assert_eq!(ruler_stride(10.0), 5);
+        assert_eq!(ruler_stride(4.0), 10);
+        assert_eq!(ruler_stride(1.0), 50);
+        assert_eq!(ruler_stride(0.1), 500);
+    }
+}
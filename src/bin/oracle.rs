@@ -0,0 +1,66 @@
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::exit;
+
+use clap::Parser;
+
+use robots::trace::{check_trace, TraceRecord};
+
+/// Replays a recorded session with the game rules and reports the first turn
+/// in which the server departed from them.
+#[derive(Parser, Debug, Clone)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Seed the server was started with.
+    #[clap(short, long)]
+    seed: u32,
+
+    /// Initial blocks the server was started with, since `Hello` does not include them.
+    #[clap(short = 'k', long)]
+    initial_blocks: u16,
+
+    /// File with the trace, one JSON record per line. It is read from standard input if absent.
+    trace: Option<PathBuf>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let reader: Box<dyn BufRead> = match &args.trace {
+        Some(path) => match std::fs::File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => {
+                eprintln!("Cannot read {}: {}", path.display(), e);
+                exit(1)
+            }
+        },
+        None => Box::new(BufReader::new(std::io::stdin())),
+    };
+
+    let mut records = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.unwrap_or_else(|e| {
+            eprintln!("Cannot read the trace: {}", e);
+            exit(1)
+        });
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str::<TraceRecord>(&line).unwrap_or_else(|e| {
+            eprintln!("Line {}: invalid record: {}", index + 1, e);
+            exit(1)
+        });
+        records.push(record);
+    }
+
+    match check_trace(&records, args.seed, args.initial_blocks) {
+        Ok(summary) => println!(
+            "OK: {} games, {} turns follow the rules",
+            summary.games, summary.turns
+        ),
+        Err(divergence) => {
+            println!("{}", divergence);
+            exit(1)
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::exit;
+
+use clap::Parser;
+
+use robots::game::GameParameters;
+use robots::serialize::serializer;
+use robots::simulation::{simulate, Script};
+use robots::trace::to_trace;
+use robots::{Player, PlayerName, ServerName};
+
+/// Plays a whole match without any sockets and prints every message the server sends.
+#[derive(Parser, Debug, Clone)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(short, long)]
+    bomb_timer: u16,
+
+    #[clap(short = 'c', long)]
+    players_count: u8,
+
+    #[clap(short, long)]
+    explosion_radius: u16,
+
+    #[clap(short = 'k', long)]
+    initial_blocks: u16,
+
+    #[clap(short = 'l', long)]
+    game_length: u16,
+
+    #[clap(short = 'n', long, default_value = "simulator")]
+    server_name: ServerName,
+
+    #[clap(short, long)]
+    seed: u32,
+
+    #[clap(short = 'x', long)]
+    size_x: u16,
+
+    #[clap(short = 'y', long)]
+    size_y: u16,
+
+    /// File with the actions of the players, one line per turn starting from turn 1,
+    /// e.g. `up bomb - block` for four players. No actions are taken if it is absent.
+    #[clap(short = 'i', long)]
+    script: Option<PathBuf>,
+
+    /// Names of the players in the order they join. Defaults to `player<id>`.
+    #[clap(long = "player-name")]
+    player_names: Vec<PlayerName>,
+
+    /// Print the messages in the binary protocol format instead of JSON lines.
+    #[clap(long)]
+    binary: bool,
+
+    /// Print a trace for the oracle, with the moves of the players interleaved, instead of
+    /// the bare server messages.
+    #[clap(long, conflicts_with = "binary")]
+    trace: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let parameters = GameParameters {
+        server_name: args.server_name.clone(),
+        players_count: args.players_count,
+        size_x: args.size_x,
+        size_y: args.size_y,
+        game_length: args.game_length,
+        explosion_radius: args.explosion_radius,
+        bomb_timer: args.bomb_timer,
+        initial_blocks: args.initial_blocks,
+    };
+
+    let players = (0..args.players_count)
+        .map(|id| Player {
+            name: args
+                .player_names
+                .get(id as usize)
+                .cloned()
+                .unwrap_or_else(|| format!("player{}", id).parse().unwrap()),
+            address: format!("127.0.0.1:{}", 10000 + id as u16).parse().unwrap(),
+        })
+        .collect::<Vec<_>>();
+
+    let script = match &args.script {
+        Some(path) => {
+            let input = std::fs::read_to_string(path).unwrap_or_else(|e| {
+                eprintln!("Cannot read {}: {}", path.display(), e);
+                exit(1)
+            });
+            Script::parse(&input, args.players_count).unwrap_or_else(|e| {
+                eprintln!("Invalid script: {}", e);
+                exit(1)
+            })
+        }
+        None => Script::default(),
+    };
+
+    let messages = simulate(&parameters, args.seed, &players, &script).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1)
+    });
+
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    if args.trace {
+        for record in to_trace(messages, &script) {
+            if let Err(e) = writeln!(stdout, "{}", serde_json::to_string(&record).unwrap()) {
+                eprintln!("{}", e);
+                exit(1)
+            }
+        }
+        return;
+    }
+    for message in messages {
+        let result = if args.binary {
+            stdout.write_all(&serializer::to_bytes(&message))
+        } else {
+            writeln!(stdout, "{}", serde_json::to_string(&message).unwrap())
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            exit(1)
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use clap::Parser;
+
+use robots::diff::diff_traces;
+use robots::trace::TraceRecord;
+use robots::ServerMessage;
+
+/// Compares the messages sent by two servers and prints the first one which differs
+/// in a way the protocol does not allow.
+#[derive(Parser, Debug, Clone)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Trace of the reference server.
+    reference: PathBuf,
+
+    /// Trace of the tested server.
+    tested: PathBuf,
+
+    /// Do not compare player addresses.
+    #[clap(long)]
+    ignore_addresses: bool,
+}
+
+fn read_messages(path: &Path) -> Vec<ServerMessage> {
+    let input = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Cannot read {}: {}", path.display(), e);
+        exit(2)
+    });
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        // Traces from the oracle and bare messages from the simulator are both accepted.
+        // `#[serde(untagged)]` cannot be used, since it does not support integer map keys.
+        .filter_map(|(index, line)| match serde_json::from_str(line) {
+            Ok(TraceRecord::Server(message)) => Some(message),
+            Ok(TraceRecord::Client { .. }) => None,
+            Err(_) => match serde_json::from_str(line) {
+                Ok(message) => Some(message),
+                Err(e) => {
+                    eprintln!("{}:{}: invalid record: {}", path.display(), index + 1, e);
+                    exit(2)
+                }
+            },
+        })
+        .collect()
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let reference = read_messages(&args.reference);
+    let tested = read_messages(&args.tested);
+    match diff_traces(&reference, &tested, args.ignore_addresses) {
+        None => println!("Traces match ({} messages)", reference.len()),
+        Some(diff) => {
+            print!("{}", diff);
+            exit(1)
+        }
+    }
+}
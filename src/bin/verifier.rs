@@ -10,6 +10,7 @@ use serde::de::DeserializeOwned;
 use tracing::{error, info, Level};
 
 use robots::serialize::deserializer::Deserializer;
+use robots::serialize::read::IoRead;
 use robots::serialize::{deserializer, DeserError};
 use robots::{ClientMessage, DisplayMessage, InputMessage, ServerMessage, MAX_UDP_LENGTH};
 
@@ -63,7 +64,7 @@ fn deserialize_message_from_buffer<T: DeserializeOwned + Debug>(
 }
 
 fn deserialize_message_from_stream<T: DeserializeOwned + Debug>(
-    deserialize: &mut Deserializer<TcpStream>,
+    deserialize: &mut Deserializer<IoRead<TcpStream>>,
     address: SocketAddr,
 ) {
     loop {
@@ -0,0 +1,76 @@
+use crate::random::Random;
+use crate::Position;
+
+/// Dimensions of a non-empty board. Only positions inside it can be created through it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Board {
+    size_x: u16,
+    size_y: u16,
+}
+
+impl Board {
+    /// Returns `None` if either of the dimensions is zero.
+    pub fn new(size_x: u16, size_y: u16) -> Option<Board> {
+        if size_x == 0 || size_y == 0 {
+            None
+        } else {
+            Some(Board { size_x, size_y })
+        }
+    }
+
+    pub fn size_x(&self) -> u16 {
+        self.size_x
+    }
+
+    pub fn size_y(&self) -> u16 {
+        self.size_y
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        position.0 < self.size_x && position.1 < self.size_y
+    }
+
+    pub fn position(&self, x: u16, y: u16) -> Option<Position> {
+        let position = Position(x, y);
+        self.contains(position).then_some(position)
+    }
+
+    /// Draws a position the way the server does it (README 2.7): first `x`, then `y`.
+    pub fn random_position(&self, random: &mut Random) -> Position {
+        let x = random.next_u32() % self.size_x as u32;
+        let y = random.next_u32() % self.size_y as u32;
+        Position(x as u16, y as u16)
+    }
+
+    /// All positions on the board, row by row from the bottom.
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let size_x = self.size_x;
+        (0..self.size_y).flat_map(move |y| (0..size_x).map(move |x| Position(x, y)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rejects_empty_board() {
+        assert_eq!(Board::new(0, 5), None);
+        assert_eq!(Board::new(5, 0), None);
+    }
+
+    #[test]
+    fn positions_are_in_bounds() {
+        let board = Board::new(3, 2).unwrap();
+        assert_eq!(board.position(2, 1), Some(Position(2, 1)));
+        assert_eq!(board.position(3, 1), None);
+        assert_eq!(board.position(0, 2), None);
+        assert_eq!(board.positions().count(), 6);
+        assert!(board.positions().all(|position| board.contains(position)));
+
+        let mut random = Random::new(1234);
+        for _ in 0..100 {
+            assert!(board.contains(board.random_position(&mut random)));
+        }
+    }
+}
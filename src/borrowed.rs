@@ -0,0 +1,210 @@
+//! Variants of the message types which borrow their strings from the input buffer.
+//!
+//! They are encoded exactly like their owned counterparts, but decoding them with
+//! [`from_bytes`](crate::serialize::deserializer::from_bytes) does not allocate for names.
+//! Decoding them from a stream is not possible, since there is nothing to borrow from.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::names::NameTooLong;
+use crate::{Bomb, Direction, Event, PlayerId, Position, Score};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Player<'a> {
+    pub name: &'a str,
+    pub address: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ClientMessage<'a> {
+    Join { name: &'a str },
+    PlaceBomb,
+    PlaceBlock,
+    Move { direction: Direction },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServerMessage<'a> {
+    Hello {
+        server_name: &'a str,
+        players_count: u8,
+        size_x: u16,
+        size_y: u16,
+        game_length: u16,
+        explosion_radius: u16,
+        bomb_timer: u16,
+    },
+    AcceptedPlayer {
+        id: PlayerId,
+        #[serde(borrow)]
+        player: Player<'a>,
+    },
+    GameStarted {
+        #[serde(borrow)]
+        players: BTreeMap<PlayerId, Player<'a>>,
+    },
+    Turn {
+        turn: u16,
+        events: Vec<Event>,
+    },
+    GameEnded {
+        scores: BTreeMap<PlayerId, Score>,
+    },
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayMessage<'a> {
+    Lobby {
+        server_name: &'a str,
+        players_count: u8,
+        size_x: u16,
+        size_y: u16,
+        game_length: u16,
+        explosion_radius: u16,
+        bomb_timer: u16,
+        #[serde(borrow)]
+        players: BTreeMap<PlayerId, Player<'a>>,
+    },
+    Game {
+        server_name: &'a str,
+        size_x: u16,
+        size_y: u16,
+        game_length: u16,
+        turn: u16,
+        #[serde(borrow)]
+        players: BTreeMap<PlayerId, Player<'a>>,
+        player_positions: BTreeMap<PlayerId, Position>,
+        blocks: HashSet<Position>,
+        bombs: HashSet<Bomb>,
+        explosions: HashSet<Position>,
+        scores: BTreeMap<PlayerId, Score>,
+    },
+}
+
+// Strings decoded by our deserializer always fit in the owned types, but hand-built
+// borrowed messages may not, hence the conversions below are fallible.
+
+fn to_owned_players(
+    players: BTreeMap<PlayerId, Player>,
+) -> Result<BTreeMap<PlayerId, crate::Player>, NameTooLong> {
+    players
+        .into_iter()
+        .map(|(id, player)| Ok((id, player.try_into()?)))
+        .collect()
+}
+
+impl TryFrom<Player<'_>> for crate::Player {
+    type Error = NameTooLong;
+
+    fn try_from(player: Player) -> Result<crate::Player, NameTooLong> {
+        Ok(crate::Player {
+            name: player.name.parse()?,
+            address: player.address.parse()?,
+        })
+    }
+}
+
+impl TryFrom<ClientMessage<'_>> for crate::ClientMessage {
+    type Error = NameTooLong;
+
+    fn try_from(message: ClientMessage) -> Result<crate::ClientMessage, NameTooLong> {
+        Ok(match message {
+            ClientMessage::Join { name } => crate::ClientMessage::Join {
+                name: name.parse()?,
+            },
+            ClientMessage::PlaceBomb => crate::ClientMessage::PlaceBomb,
+            ClientMessage::PlaceBlock => crate::ClientMessage::PlaceBlock,
+            ClientMessage::Move { direction } => crate::ClientMessage::Move { direction },
+        })
+    }
+}
+
+impl TryFrom<ServerMessage<'_>> for crate::ServerMessage {
+    type Error = NameTooLong;
+
+    fn try_from(message: ServerMessage) -> Result<crate::ServerMessage, NameTooLong> {
+        Ok(match message {
+            ServerMessage::Hello {
+                server_name,
+                players_count,
+                size_x,
+                size_y,
+                game_length,
+                explosion_radius,
+                bomb_timer,
+            } => crate::ServerMessage::Hello {
+                server_name: server_name.parse()?,
+                players_count,
+                size_x,
+                size_y,
+                game_length,
+                explosion_radius,
+                bomb_timer,
+            },
+            ServerMessage::AcceptedPlayer { id, player } => crate::ServerMessage::AcceptedPlayer {
+                id,
+                player: player.try_into()?,
+            },
+            ServerMessage::GameStarted { players } => crate::ServerMessage::GameStarted {
+                players: to_owned_players(players)?,
+            },
+            ServerMessage::Turn { turn, events } => crate::ServerMessage::Turn { turn, events },
+            ServerMessage::GameEnded { scores } => crate::ServerMessage::GameEnded { scores },
+        })
+    }
+}
+
+impl TryFrom<DisplayMessage<'_>> for crate::DisplayMessage {
+    type Error = NameTooLong;
+
+    fn try_from(message: DisplayMessage) -> Result<crate::DisplayMessage, NameTooLong> {
+        Ok(match message {
+            DisplayMessage::Lobby {
+                server_name,
+                players_count,
+                size_x,
+                size_y,
+                game_length,
+                explosion_radius,
+                bomb_timer,
+                players,
+            } => crate::DisplayMessage::Lobby {
+                server_name: server_name.parse()?,
+                players_count,
+                size_x,
+                size_y,
+                game_length,
+                explosion_radius,
+                bomb_timer,
+                players: to_owned_players(players)?,
+            },
+            DisplayMessage::Game {
+                server_name,
+                size_x,
+                size_y,
+                game_length,
+                turn,
+                players,
+                player_positions,
+                blocks,
+                bombs,
+                explosions,
+                scores,
+            } => crate::DisplayMessage::Game {
+                server_name: server_name.parse()?,
+                size_x,
+                size_y,
+                game_length,
+                turn,
+                players: to_owned_players(players)?,
+                player_positions,
+                blocks,
+                bombs,
+                explosions,
+                scores,
+            },
+        })
+    }
+}
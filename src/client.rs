@@ -0,0 +1,379 @@
+//! What a client shows in the GUI after each message from the server (README 3 and 7).
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::game::explosion;
+use crate::{
+    Board, Bomb, BombId, ClientMessage, DisplayMessage, Event, InputMessage, Player, PlayerId,
+    PlayerName, Position, Score, ServerMessage, ServerName,
+};
+
+/// Contents of `Hello`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Settings {
+    server_name: ServerName,
+    players_count: u8,
+    size_x: u16,
+    size_y: u16,
+    game_length: u16,
+    explosion_radius: u16,
+    bomb_timer: u16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct GameView {
+    turn: u16,
+    positions: BTreeMap<PlayerId, Position>,
+    blocks: HashSet<Position>,
+    bombs: BTreeMap<BombId, Bomb>,
+    explosions: HashSet<Position>,
+    scores: BTreeMap<PlayerId, Score>,
+}
+
+/// State of a client, fed with the messages of the server in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientState {
+    settings: Option<Settings>,
+    players: BTreeMap<PlayerId, Player>,
+    game: Option<GameView>,
+}
+
+impl ClientState {
+    pub fn new() -> ClientState {
+        ClientState::default()
+    }
+
+    /// Whether `GameStarted` was received and the game has not ended yet.
+    pub fn in_game(&self) -> bool {
+        self.game.is_some()
+    }
+
+    /// The message sent to the server in reaction to an input from the GUI:
+    /// `Join` in the lobby, the input itself during a game.
+    pub fn input(&self, input: InputMessage, name: &PlayerName) -> ClientMessage {
+        if self.in_game() {
+            input.into()
+        } else {
+            ClientMessage::Join { name: name.clone() }
+        }
+    }
+
+    /// Applies a message of the server. Returns the message for the GUI, if the state changed.
+    /// Messages which make no sense in the current state are ignored.
+    pub fn apply(&mut self, message: &ServerMessage) -> Option<DisplayMessage> {
+        match message {
+            ServerMessage::Hello {
+                server_name,
+                players_count,
+                size_x,
+                size_y,
+                game_length,
+                explosion_radius,
+                bomb_timer,
+            } => {
+                self.settings = Some(Settings {
+                    server_name: server_name.clone(),
+                    players_count: *players_count,
+                    size_x: *size_x,
+                    size_y: *size_y,
+                    game_length: *game_length,
+                    explosion_radius: *explosion_radius,
+                    bomb_timer: *bomb_timer,
+                });
+                self.display()
+            }
+            ServerMessage::AcceptedPlayer { id, player } => {
+                if self.in_game() {
+                    return None;
+                }
+                self.players.insert(*id, player.clone());
+                self.display()
+            }
+            ServerMessage::GameStarted { players } => {
+                self.players = players.clone();
+                self.game = Some(GameView {
+                    scores: players.keys().map(|&id| (id, Score::default())).collect(),
+                    ..GameView::default()
+                });
+                None
+            }
+            ServerMessage::Turn { turn, events } => {
+                let settings = self.settings.as_ref()?;
+                let board = Board::new(settings.size_x, settings.size_y)?;
+                let game = self.game.as_mut()?;
+                game.play_turn(*turn, events, settings, &board);
+                self.display()
+            }
+            ServerMessage::GameEnded { .. } => {
+                self.game = None;
+                self.players.clear();
+                self.display()
+            }
+        }
+    }
+
+    /// The current state as a message for the GUI.
+    pub fn display(&self) -> Option<DisplayMessage> {
+        let settings = self.settings.as_ref()?;
+        Some(match &self.game {
+            None => DisplayMessage::Lobby {
+                server_name: settings.server_name.clone(),
+                players_count: settings.players_count,
+                size_x: settings.size_x,
+                size_y: settings.size_y,
+                game_length: settings.game_length,
+                explosion_radius: settings.explosion_radius,
+                bomb_timer: settings.bomb_timer,
+                players: self.players.clone(),
+            },
+            Some(game) => DisplayMessage::Game {
+                server_name: settings.server_name.clone(),
+                size_x: settings.size_x,
+                size_y: settings.size_y,
+                game_length: settings.game_length,
+                turn: game.turn,
+                players: self.players.clone(),
+                player_positions: game.positions.clone(),
+                blocks: game.blocks.clone(),
+                bombs: game.bombs.values().cloned().collect(),
+                explosions: game.explosions.clone(),
+                scores: game.scores.clone(),
+            },
+        })
+    }
+}
+
+impl GameView {
+    fn play_turn(&mut self, turn: u16, events: &[Event], settings: &Settings, board: &Board) {
+        self.turn = turn;
+        self.explosions.clear();
+        for bomb in self.bombs.values_mut() {
+            bomb.timer = bomb.timer.saturating_sub(1);
+        }
+
+        // Explosions see the blocks as they were before the turn (README 2.8).
+        let blocks_before = self.blocks.clone();
+        let mut killed = BTreeSet::new();
+        for event in events {
+            match event {
+                Event::BombPlaced { id, position } => {
+                    self.bombs.insert(
+                        *id,
+                        Bomb {
+                            position: *position,
+                            timer: settings.bomb_timer,
+                        },
+                    );
+                }
+                Event::BombExploded {
+                    id,
+                    killed: robots,
+                    blocks_destroyed,
+                } => {
+                    if let Some(bomb) = self.bombs.remove(id) {
+                        self.explosions.extend(explosion(
+                            bomb.position,
+                            settings.explosion_radius,
+                            board,
+                            &blocks_before,
+                        ));
+                    }
+                    killed.extend(robots.iter().copied());
+                    self.blocks
+                        .retain(|position| !blocks_destroyed.contains(position));
+                }
+                Event::PlayerMoved { id, position } => {
+                    self.positions.insert(*id, *position);
+                }
+                Event::BlockPlaced { position } => {
+                    self.blocks.insert(*position);
+                }
+            }
+        }
+        // A robot killed by several bombs in one turn dies only once.
+        for id in killed {
+            self.scores.entry(id).or_default().deaths += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::GameParameters;
+    use crate::simulation::{simulate, Script};
+    use crate::Direction;
+
+    fn parameters() -> GameParameters {
+        GameParameters {
+            server_name: "test".parse().unwrap(),
+            players_count: 2,
+            size_x: 6,
+            size_y: 5,
+            game_length: 6,
+            explosion_radius: 2,
+            bomb_timer: 2,
+            initial_blocks: 6,
+        }
+    }
+
+    fn players() -> Vec<Player> {
+        (0..2)
+            .map(|id| Player {
+                name: format!("player{}", id).parse().unwrap(),
+                address: "127.0.0.1:2022".parse().unwrap(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lobby() {
+        let messages = simulate(&parameters(), 1, &players(), &Script::default()).unwrap();
+        let mut state = ClientState::new();
+        let name = PlayerName::new("me").unwrap();
+        assert!(matches!(
+            state.apply(&messages[0]),
+            Some(DisplayMessage::Lobby { players, .. }) if players.is_empty()
+        ));
+        assert_eq!(
+            state.input(InputMessage::PlaceBomb, &name),
+            ClientMessage::Join { name: name.clone() }
+        );
+        assert!(matches!(
+            state.apply(&messages[1]),
+            Some(DisplayMessage::Lobby { players, .. }) if players.len() == 1
+        ));
+        state.apply(&messages[2]);
+        assert_eq!(state.apply(&messages[3]), None);
+        assert!(state.in_game());
+        assert_eq!(
+            state.input(
+                InputMessage::Move {
+                    direction: Direction::Up
+                },
+                &name
+            ),
+            ClientMessage::Move {
+                direction: Direction::Up
+            }
+        );
+    }
+
+    #[test]
+    fn scores_match_the_server() {
+        let script = Script::parse("bomb bomb\n- -\n- -\nbomb block\n- bomb", 2).unwrap();
+        let messages = simulate(&parameters(), 7, &players(), &script).unwrap();
+        let mut state = ClientState::new();
+        let mut last = None;
+        for message in &messages {
+            if let ServerMessage::GameEnded { scores } = message {
+                match &last {
+                    Some(DisplayMessage::Game {
+                        scores: shown,
+                        turn,
+                        ..
+                    }) => {
+                        assert_eq!(shown, scores);
+                        assert_eq!(*turn, 6);
+                    }
+                    other => panic!("unexpected display {:?}", other),
+                }
+            }
+            if let Some(display) = state.apply(message) {
+                last = Some(display);
+            }
+        }
+        assert!(matches!(last, Some(DisplayMessage::Lobby { .. })));
+    }
+
+    #[test]
+    fn explosions_and_timers() {
+        let mut state = ClientState::new();
+        state.apply(&parameters().hello());
+        let players = players()
+            .into_iter()
+            .enumerate()
+            .map(|(id, player)| (PlayerId(id as u8), player))
+            .collect::<BTreeMap<_, _>>();
+        state.apply(&ServerMessage::GameStarted { players });
+        let turn = |turn, events| ServerMessage::Turn { turn, events };
+        state.apply(&turn(
+            0,
+            vec![
+                Event::PlayerMoved {
+                    id: PlayerId(0),
+                    position: Position(0, 0),
+                },
+                Event::PlayerMoved {
+                    id: PlayerId(1),
+                    position: Position(5, 4),
+                },
+                Event::BlockPlaced {
+                    position: Position(2, 0),
+                },
+            ],
+        ));
+        let placed = state.apply(&turn(
+            1,
+            vec![Event::BombPlaced {
+                id: BombId(0),
+                position: Position(0, 0),
+            }],
+        ));
+        assert!(matches!(
+            placed,
+            Some(DisplayMessage::Game { bombs, .. })
+                if bombs == HashSet::from([Bomb { position: Position(0, 0), timer: 2 }])
+        ));
+        let ticked = state.apply(&turn(2, vec![]));
+        assert!(matches!(
+            ticked,
+            Some(DisplayMessage::Game { bombs, .. })
+                if bombs == HashSet::from([Bomb { position: Position(0, 0), timer: 1 }])
+        ));
+        let exploded = state.apply(&turn(
+            3,
+            vec![
+                Event::BombExploded {
+                    id: BombId(0),
+                    killed: vec![PlayerId(0)],
+                    blocks_destroyed: BTreeSet::from([Position(2, 0)]),
+                },
+                Event::PlayerMoved {
+                    id: PlayerId(0),
+                    position: Position(3, 3),
+                },
+            ],
+        ));
+        match exploded {
+            Some(DisplayMessage::Game {
+                bombs,
+                blocks,
+                explosions,
+                scores,
+                ..
+            }) => {
+                assert!(bombs.is_empty());
+                assert!(blocks.is_empty());
+                assert_eq!(
+                    explosions,
+                    HashSet::from([
+                        Position(0, 0),
+                        Position(1, 0),
+                        Position(2, 0),
+                        Position(0, 1),
+                        Position(0, 2),
+                    ])
+                );
+                assert_eq!(scores[&PlayerId(0)], Score { deaths: 1 });
+                assert_eq!(scores[&PlayerId(1)], Score { deaths: 0 });
+            }
+            other => panic!("unexpected display {:?}", other),
+        }
+        // Explosions are shown only for the turn in which they happened.
+        assert!(matches!(
+            state.apply(&turn(4, vec![])),
+            Some(DisplayMessage::Game { explosions, .. }) if explosions.is_empty()
+        ));
+    }
+}
@@ -0,0 +1,245 @@
+//! Comparing the messages sent by two servers playing the same session.
+
+use std::fmt;
+
+use crate::trace::normalize;
+use crate::{PlayerAddress, ServerMessage};
+
+/// Lines of context shown around each change.
+const CONTEXT: usize = 2;
+
+/// The first pair of messages which differ in a way the protocol does not allow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceDiff {
+    /// Index of the message in both traces.
+    pub index: usize,
+    /// `None` if the trace ended before this message.
+    pub left: Option<ServerMessage>,
+    pub right: Option<ServerMessage>,
+}
+
+/// Aligns two traces message by message and returns the first divergence, if any.
+///
+/// Both traces are normalized first (see [`normalize`]). With `ignore_addresses`,
+/// player addresses are not compared, since clients connecting to two servers
+/// usually get different ports.
+pub fn diff_traces(
+    left: &[ServerMessage],
+    right: &[ServerMessage],
+    ignore_addresses: bool,
+) -> Option<TraceDiff> {
+    let prepare = |message: &ServerMessage| {
+        let mut message = normalize(message);
+        if ignore_addresses {
+            clear_addresses(&mut message);
+        }
+        message
+    };
+    (0..left.len().max(right.len())).find_map(|index| {
+        let l = left.get(index).map(prepare);
+        let r = right.get(index).map(prepare);
+        (l != r).then_some(TraceDiff {
+            index,
+            left: l,
+            right: r,
+        })
+    })
+}
+
+fn clear_addresses(message: &mut ServerMessage) {
+    let players = match message {
+        ServerMessage::AcceptedPlayer { player, .. } => vec![player],
+        ServerMessage::GameStarted { players } => players.values_mut().collect(),
+        _ => Vec::new(),
+    };
+    for player in players {
+        player.address = PlayerAddress::new("").expect("empty address is valid");
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Line diff based on the longest common subsequence. Messages are small, so the
+/// quadratic table is not a concern.
+fn diff_lines<'a>(left: &[&'a str], right: &[&'a str]) -> Vec<Line<'a>> {
+    let mut lcs = vec![vec![0usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lcs[i][j] = if left[i] == right[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i] == right[j] {
+            lines.push(Line::Same(left[i]));
+            i += 1;
+            j += 1;
+        } else if i < left.len() && (j == right.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(Line::Removed(left[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(right[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+impl fmt::Display for TraceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.left.as_ref().or(self.right.as_ref()) {
+            Some(ServerMessage::Turn { turn, .. }) => {
+                writeln!(f, "Message {} (Turn {}) differs:", self.index, turn)?
+            }
+            _ => writeln!(f, "Message {} differs:", self.index)?,
+        }
+        let (left, right) = match (&self.left, &self.right) {
+            (Some(left), Some(right)) => (format!("{:#?}", left), format!("{:#?}", right)),
+            (Some(left), None) => return writeln!(f, "only in the first trace: {:?}", left),
+            (None, Some(right)) => return writeln!(f, "only in the second trace: {:?}", right),
+            (None, None) => return Ok(()),
+        };
+        let left = left.lines().collect::<Vec<_>>();
+        let right = right.lines().collect::<Vec<_>>();
+        let lines = diff_lines(&left, &right);
+
+        // Print only the changes and a few lines around them.
+        let near_change = |index: usize| {
+            let from = index.saturating_sub(CONTEXT);
+            let to = (index + CONTEXT + 1).min(lines.len());
+            lines[from..to]
+                .iter()
+                .any(|line| !matches!(line, Line::Same(_)))
+        };
+        let mut skipped = false;
+        for (index, line) in lines.iter().enumerate() {
+            if !near_change(index) {
+                skipped = true;
+                continue;
+            }
+            if skipped {
+                writeln!(f, "  ...")?;
+                skipped = false;
+            }
+            match line {
+                Line::Same(line) => writeln!(f, "  {}", line)?,
+                Line::Removed(line) => writeln!(f, "- {}", line)?,
+                Line::Added(line) => writeln!(f, "+ {}", line)?,
+            }
+        }
+        if skipped {
+            writeln!(f, "  ...")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::{BombId, Event, Player, PlayerId, Position};
+
+    fn explosion(id: u32, killed: Vec<u8>, blocks: &[Position]) -> Event {
+        Event::BombExploded {
+            id: BombId(id),
+            killed: killed.into_iter().map(PlayerId).collect(),
+            blocks_destroyed: blocks.iter().copied().collect::<BTreeSet<_>>(),
+        }
+    }
+
+    fn turn(events: Vec<Event>) -> ServerMessage {
+        ServerMessage::Turn { turn: 5, events }
+    }
+
+    #[test]
+    fn allowed_differences_are_ignored() {
+        let moved = Event::PlayerMoved {
+            id: PlayerId(0),
+            position: Position(1, 1),
+        };
+        let left = [turn(vec![
+            explosion(0, vec![0, 1], &[Position(0, 0)]),
+            explosion(1, vec![], &[]),
+            moved.clone(),
+        ])];
+        let right = [turn(vec![
+            explosion(1, vec![], &[]),
+            explosion(0, vec![1, 0], &[Position(0, 0)]),
+            moved,
+        ])];
+        assert_eq!(diff_traces(&left, &right, false), None);
+    }
+
+    #[test]
+    fn moves_before_explosions_differ() {
+        let moved = Event::PlayerMoved {
+            id: PlayerId(0),
+            position: Position(1, 1),
+        };
+        let left = [turn(vec![explosion(0, vec![], &[]), moved.clone()])];
+        let right = [turn(vec![moved, explosion(0, vec![], &[])])];
+        assert_eq!(diff_traces(&left, &right, false).unwrap().index, 0);
+    }
+
+    #[test]
+    fn addresses() {
+        let accepted = |address: &str| ServerMessage::AcceptedPlayer {
+            id: PlayerId(0),
+            player: Player {
+                name: "name".parse().unwrap(),
+                address: address.parse().unwrap(),
+            },
+        };
+        let left = [accepted("127.0.0.1:1000")];
+        let right = [accepted("[::1]:2000")];
+        assert!(diff_traces(&left, &right, false).is_some());
+        assert_eq!(diff_traces(&left, &right, true), None);
+    }
+
+    #[test]
+    fn minimal_diff() {
+        let left = [
+            turn(vec![]),
+            turn(vec![explosion(
+                0,
+                vec![],
+                &[Position(1, 2), Position(3, 4)],
+            )]),
+        ];
+        let right = [
+            turn(vec![]),
+            turn(vec![explosion(0, vec![], &[Position(1, 2)])]),
+        ];
+        let diff = diff_traces(&left, &right, false).unwrap();
+        assert_eq!(diff.index, 1);
+        let text = diff.to_string();
+        let changed = text
+            .lines()
+            .filter(|line| line.starts_with('-') || line.starts_with('+'))
+            .collect::<Vec<_>>();
+        assert!(!changed.is_empty());
+        assert!(changed.iter().all(|line| line.starts_with('-')));
+        assert!(text.contains("..."));
+    }
+
+    #[test]
+    fn different_lengths() {
+        let left = [turn(vec![])];
+        let diff = diff_traces(&left, &[], false).unwrap();
+        assert_eq!(diff.right, None);
+        assert!(diff.to_string().contains("only in the first trace"));
+    }
+}
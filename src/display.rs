@@ -0,0 +1,231 @@
+//! Consistency checks of `DisplayMessage`s, so that the GUI can point out bugs of the client
+//! which sent them instead of silently drawing less.
+
+use thiserror::Error;
+
+use crate::{Board, DisplayMessage, PlayerId, Position};
+
+/// Something in a message which cannot be right, whatever the state of the game.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum Inconsistency {
+    #[error("Board {size_x}x{size_y} has no cells")]
+    EmptyBoard { size_x: u16, size_y: u16 },
+    #[error("Turn {turn} is after the end of the game ({game_length} turns)")]
+    TurnAfterEnd { turn: u16, game_length: u16 },
+    #[error("{count} players in the lobby, but only {players_count} are needed")]
+    TooManyPlayers { count: usize, players_count: u8 },
+    #[error("Unknown player {} in {field}", .id.0)]
+    UnknownPlayer { id: PlayerId, field: &'static str },
+    #[error("Player {} has no position", .id.0)]
+    MissingPosition { id: PlayerId },
+    #[error("Player {} has no score", .id.0)]
+    MissingScore { id: PlayerId },
+    #[error("Robot of player {} at ({}, {}) is off the board", .id.0, .position.0, .position.1)]
+    RobotOffBoard { id: PlayerId, position: Position },
+    #[error("{what} at ({}, {}) is off the board", .position.0, .position.1)]
+    OffBoard {
+        what: &'static str,
+        position: Position,
+    },
+}
+
+/// Everything wrong with the message, in the order of its fields.
+pub fn inconsistencies(message: &DisplayMessage) -> Vec<Inconsistency> {
+    let mut found = Vec::new();
+    match message {
+        DisplayMessage::Lobby {
+            players_count,
+            size_x,
+            size_y,
+            players,
+            ..
+        } => {
+            if Board::new(*size_x, *size_y).is_none() {
+                found.push(Inconsistency::EmptyBoard {
+                    size_x: *size_x,
+                    size_y: *size_y,
+                });
+            }
+            if players.len() > *players_count as usize {
+                found.push(Inconsistency::TooManyPlayers {
+                    count: players.len(),
+                    players_count: *players_count,
+                });
+            }
+        }
+        DisplayMessage::Game {
+            size_x,
+            size_y,
+            game_length,
+            turn,
+            players,
+            player_positions,
+            blocks,
+            bombs,
+            explosions,
+            scores,
+            ..
+        } => {
+            let board = Board::new(*size_x, *size_y);
+            if board.is_none() {
+                found.push(Inconsistency::EmptyBoard {
+                    size_x: *size_x,
+                    size_y: *size_y,
+                });
+            }
+            let on_board =
+                |position: Position| board.as_ref().is_some_and(|b| b.contains(position));
+            if turn > game_length {
+                found.push(Inconsistency::TurnAfterEnd {
+                    turn: *turn,
+                    game_length: *game_length,
+                });
+            }
+
+            for id in players.keys() {
+                if !player_positions.contains_key(id) {
+                    found.push(Inconsistency::MissingPosition { id: *id });
+                }
+                if !scores.contains_key(id) {
+                    found.push(Inconsistency::MissingScore { id: *id });
+                }
+            }
+            for (&id, &position) in player_positions {
+                if !players.contains_key(&id) {
+                    found.push(Inconsistency::UnknownPlayer {
+                        id,
+                        field: "player_positions",
+                    });
+                }
+                if !on_board(position) {
+                    found.push(Inconsistency::RobotOffBoard { id, position });
+                }
+            }
+            for &id in scores.keys() {
+                if !players.contains_key(&id) {
+                    found.push(Inconsistency::UnknownPlayer {
+                        id,
+                        field: "scores",
+                    });
+                }
+            }
+
+            // Sets have no order, so the positions are sorted to keep the list stable.
+            let mut off_board = |what, positions: &mut dyn Iterator<Item = Position>| {
+                let mut positions = positions
+                    .filter(|&position| !on_board(position))
+                    .collect::<Vec<_>>();
+                positions.sort();
+                found.extend(
+                    positions
+                        .into_iter()
+                        .map(|position| Inconsistency::OffBoard { what, position }),
+                );
+            };
+            off_board("Block", &mut blocks.iter().copied());
+            off_board("Bomb", &mut bombs.iter().map(|bomb| bomb.position));
+            off_board("Explosion", &mut explosions.iter().copied());
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashSet};
+
+    use super::*;
+    use crate::{Bomb, Player, Score};
+
+    fn game() -> DisplayMessage {
+        let player = Player {
+            name: "robot".parse().unwrap(),
+            address: "127.0.0.1:2022".parse().unwrap(),
+        };
+        DisplayMessage::Game {
+            server_name: "server".parse().unwrap(),
+            size_x: 4,
+            size_y: 3,
+            game_length: 10,
+            turn: 2,
+            players: BTreeMap::from([(PlayerId(0), player.clone()), (PlayerId(1), player)]),
+            player_positions: BTreeMap::from([
+                (PlayerId(0), Position(0, 0)),
+                (PlayerId(1), Position(3, 2)),
+            ]),
+            blocks: HashSet::from([Position(1, 1)]),
+            bombs: HashSet::from([Bomb {
+                position: Position(2, 2),
+                timer: 1,
+            }]),
+            explosions: HashSet::new(),
+            scores: BTreeMap::from([
+                (PlayerId(0), Score::default()),
+                (PlayerId(1), Score::default()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn consistent_game() {
+        assert_eq!(inconsistencies(&game()), vec![]);
+    }
+
+    #[test]
+    fn inconsistent_game() {
+        let mut message = game();
+        if let DisplayMessage::Game {
+            turn,
+            player_positions,
+            bombs,
+            explosions,
+            scores,
+            ..
+        } = &mut message
+        {
+            *turn = 11;
+            player_positions.insert(PlayerId(1), Position(4, 0));
+            player_positions.insert(PlayerId(7), Position(1, 0));
+            bombs.insert(Bomb {
+                position: Position(0, 3),
+                timer: 2,
+            });
+            explosions.insert(Position(9, 9));
+            scores.remove(&PlayerId(0));
+        }
+        assert_eq!(
+            inconsistencies(&message),
+            vec![
+                Inconsistency::TurnAfterEnd {
+                    turn: 11,
+                    game_length: 10
+                },
+                Inconsistency::MissingScore { id: PlayerId(0) },
+                Inconsistency::RobotOffBoard {
+                    id: PlayerId(1),
+                    position: Position(4, 0)
+                },
+                Inconsistency::UnknownPlayer {
+                    id: PlayerId(7),
+                    field: "player_positions"
+                },
+                Inconsistency::OffBoard {
+                    what: "Bomb",
+                    position: Position(0, 3)
+                },
+                Inconsistency::OffBoard {
+                    what: "Explosion",
+                    position: Position(9, 9)
+                },
+            ]
+        );
+        assert_eq!(
+            Inconsistency::RobotOffBoard {
+                id: PlayerId(1),
+                position: Position(4, 0)
+            }
+            .to_string(),
+            "Robot of player 1 at (4, 0) is off the board"
+        );
+    }
+}
@@ -0,0 +1,399 @@
+//! Game rules as described in README 2.7 - 2.10, without any networking.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::random::Random;
+use crate::{
+    Board, Bomb, BombId, ClientMessage, Event, Player, PlayerId, Position, Score, ServerMessage,
+    ServerName,
+};
+
+/// Server parameters which affect the course of a game (README 1.3).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameParameters {
+    pub server_name: ServerName,
+    pub players_count: u8,
+    pub size_x: u16,
+    pub size_y: u16,
+    pub game_length: u16,
+    pub explosion_radius: u16,
+    pub bomb_timer: u16,
+    pub initial_blocks: u16,
+}
+
+impl GameParameters {
+    pub fn board(&self) -> Option<Board> {
+        Board::new(self.size_x, self.size_y)
+    }
+
+    pub fn hello(&self) -> ServerMessage {
+        ServerMessage::Hello {
+            server_name: self.server_name.clone(),
+            players_count: self.players_count,
+            size_x: self.size_x,
+            size_y: self.size_y,
+            game_length: self.game_length,
+            explosion_radius: self.explosion_radius,
+            bomb_timer: self.bomb_timer,
+        }
+    }
+}
+
+/// Positions hit by a bomb exploding at `center`, in ascending order.
+///
+/// Each arm of the cross stops at the first block, which is still hit.
+/// A bomb lying on a block destroys only that block.
+pub fn explosion(
+    center: Position,
+    radius: u16,
+    board: &Board,
+    blocks: &HashSet<Position>,
+) -> BTreeSet<Position> {
+    let mut hit = BTreeSet::from([center]);
+    if blocks.contains(&center) {
+        return hit;
+    }
+    for ray in center.cross(radius, board) {
+        for position in ray {
+            hit.insert(position);
+            if blocks.contains(&position) {
+                break;
+            }
+        }
+    }
+    hit
+}
+
+/// State of a game in progress, as kept by the server (README 2.5).
+#[derive(Debug, Clone)]
+pub struct Game {
+    explosion_radius: u16,
+    bomb_timer: u16,
+    game_length: u16,
+    board: Board,
+    turn: u16,
+    players: BTreeMap<PlayerId, Player>,
+    positions: BTreeMap<PlayerId, Position>,
+    scores: BTreeMap<PlayerId, Score>,
+    blocks: HashSet<Position>,
+    bombs: BTreeMap<BombId, Bomb>,
+    next_bomb_id: u32,
+}
+
+impl Game {
+    /// Places the robots and the initial blocks (README 2.7).
+    /// Returns the game together with its `Turn 0` message.
+    pub fn start(
+        parameters: &GameParameters,
+        board: Board,
+        players: BTreeMap<PlayerId, Player>,
+        random: &mut Random,
+    ) -> (Game, ServerMessage) {
+        let mut game = Game {
+            explosion_radius: parameters.explosion_radius,
+            bomb_timer: parameters.bomb_timer,
+            game_length: parameters.game_length,
+            board,
+            turn: 0,
+            scores: players.keys().map(|&id| (id, Score::default())).collect(),
+            players,
+            positions: BTreeMap::new(),
+            blocks: HashSet::new(),
+            bombs: BTreeMap::new(),
+            next_bomb_id: 0,
+        };
+
+        let mut events = Vec::new();
+        for &id in game.players.keys() {
+            let position = board.random_position(random);
+            game.positions.insert(id, position);
+            events.push(Event::PlayerMoved { id, position });
+        }
+        for _ in 0..parameters.initial_blocks {
+            let position = board.random_position(random);
+            if game.blocks.insert(position) {
+                events.push(Event::BlockPlaced { position });
+            }
+        }
+
+        (game, ServerMessage::Turn { turn: 0, events })
+    }
+
+    /// Plays the next turn (README 2.8), given the last message of each player.
+    pub fn play_turn(
+        &mut self,
+        moves: &BTreeMap<PlayerId, ClientMessage>,
+        random: &mut Random,
+    ) -> ServerMessage {
+        let mut events = Vec::new();
+
+        let mut exploded = Vec::new();
+        for (&id, bomb) in self.bombs.iter_mut() {
+            bomb.timer = bomb.timer.saturating_sub(1);
+            if bomb.timer == 0 {
+                exploded.push(id);
+            }
+        }
+
+        // All explosions in a turn see the board as it was before any of them.
+        let mut destroyed_robots = BTreeSet::new();
+        let mut destroyed_blocks = HashSet::new();
+        for id in exploded {
+            let bomb = self.bombs.remove(&id).expect("bomb exists");
+            let hit = explosion(
+                bomb.position,
+                self.explosion_radius,
+                &self.board,
+                &self.blocks,
+            );
+            let killed = self
+                .positions
+                .iter()
+                .filter(|(_, position)| hit.contains(position))
+                .map(|(&id, _)| id)
+                .collect::<Vec<_>>();
+            let blocks_destroyed = hit
+                .into_iter()
+                .filter(|position| self.blocks.contains(position))
+                .collect::<BTreeSet<_>>();
+            destroyed_robots.extend(killed.iter().copied());
+            destroyed_blocks.extend(blocks_destroyed.iter().copied());
+            events.push(Event::BombExploded {
+                id,
+                killed,
+                blocks_destroyed,
+            });
+        }
+        self.blocks
+            .retain(|position| !destroyed_blocks.contains(position));
+
+        let ids = self.players.keys().copied().collect::<Vec<_>>();
+        for id in ids {
+            if destroyed_robots.contains(&id) {
+                let position = self.board.random_position(random);
+                self.positions.insert(id, position);
+                self.scores.entry(id).or_default().deaths += 1;
+                events.push(Event::PlayerMoved { id, position });
+            } else if let Some(message) = moves.get(&id) {
+                events.extend(self.make_move(id, message));
+            }
+        }
+
+        self.turn += 1;
+        ServerMessage::Turn {
+            turn: self.turn,
+            events,
+        }
+    }
+
+    fn make_move(&mut self, id: PlayerId, message: &ClientMessage) -> Option<Event> {
+        let position = self.positions[&id];
+        match message {
+            ClientMessage::Join { .. } => None,
+            ClientMessage::PlaceBomb => {
+                let id = BombId(self.next_bomb_id);
+                self.next_bomb_id += 1;
+                self.bombs.insert(
+                    id,
+                    Bomb {
+                        position,
+                        timer: self.bomb_timer,
+                    },
+                );
+                Some(Event::BombPlaced { id, position })
+            }
+            ClientMessage::PlaceBlock => self
+                .blocks
+                .insert(position)
+                .then_some(Event::BlockPlaced { position }),
+            ClientMessage::Move { direction } => {
+                let target = position.step(*direction, &self.board)?;
+                if self.blocks.contains(&target) {
+                    return None;
+                }
+                self.positions.insert(id, target);
+                Some(Event::PlayerMoved {
+                    id,
+                    position: target,
+                })
+            }
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.turn >= self.game_length
+    }
+
+    pub fn game_ended(&self) -> ServerMessage {
+        ServerMessage::GameEnded {
+            scores: self.scores.clone(),
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn turn(&self) -> u16 {
+        self.turn
+    }
+
+    pub fn players(&self) -> &BTreeMap<PlayerId, Player> {
+        &self.players
+    }
+
+    pub fn positions(&self) -> &BTreeMap<PlayerId, Position> {
+        &self.positions
+    }
+
+    pub fn scores(&self) -> &BTreeMap<PlayerId, Score> {
+        &self.scores
+    }
+
+    pub fn blocks(&self) -> &HashSet<Position> {
+        &self.blocks
+    }
+
+    pub fn bombs(&self) -> &BTreeMap<BombId, Bomb> {
+        &self.bombs
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Direction;
+
+    fn game(blocks: &[Position], positions: &[Position]) -> Game {
+        let players = (0..positions.len())
+            .map(|id| {
+                let player = Player {
+                    name: format!("player{}", id).parse().unwrap(),
+                    address: "127.0.0.1:2022".parse().unwrap(),
+                };
+                (PlayerId(id as u8), player)
+            })
+            .collect::<BTreeMap<_, _>>();
+        Game {
+            explosion_radius: 2,
+            bomb_timer: 2,
+            game_length: 10,
+            board: Board::new(5, 5).unwrap(),
+            turn: 1,
+            scores: players.keys().map(|&id| (id, Score::default())).collect(),
+            players,
+            positions: (0..).map(PlayerId).zip(positions.iter().copied()).collect(),
+            blocks: blocks.iter().copied().collect(),
+            bombs: BTreeMap::new(),
+            next_bomb_id: 0,
+        }
+    }
+
+    fn events(message: ServerMessage) -> Vec<Event> {
+        match message {
+            ServerMessage::Turn { events, .. } => events,
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    fn moving(direction: Direction) -> ClientMessage {
+        ClientMessage::Move { direction }
+    }
+
+    #[test]
+    fn illegal_moves_are_ignored() {
+        let mut game = game(&[Position(1, 0)], &[Position(0, 0), Position(2, 0)]);
+        let mut random = Random::new(1);
+        let moves = BTreeMap::from([
+            (PlayerId(0), moving(Direction::Right)),
+            (PlayerId(1), moving(Direction::Down)),
+        ]);
+        assert!(events(game.play_turn(&moves, &mut random)).is_empty());
+        assert_eq!(game.turn(), 2);
+    }
+
+    #[test]
+    fn robots_can_leave_blocked_field() {
+        let mut game = game(&[Position(0, 0)], &[Position(0, 0)]);
+        let mut random = Random::new(1);
+        let moves = BTreeMap::from([(PlayerId(0), moving(Direction::Up))]);
+        assert_eq!(
+            events(game.play_turn(&moves, &mut random)),
+            vec![Event::PlayerMoved {
+                id: PlayerId(0),
+                position: Position(0, 1)
+            }]
+        );
+        let moves = BTreeMap::from([(PlayerId(0), ClientMessage::PlaceBlock)]);
+        assert_eq!(
+            events(game.play_turn(&moves, &mut random)),
+            vec![Event::BlockPlaced {
+                position: Position(0, 1)
+            }]
+        );
+        assert!(events(game.play_turn(&moves, &mut random)).is_empty());
+    }
+
+    #[test]
+    fn simultaneous_explosions_see_the_same_blocks() {
+        // Two bombs next to each other inside a wall of blocks, as in README 2.8.
+        let blocks = [
+            Position(1, 1),
+            Position(2, 1),
+            Position(3, 1),
+            Position(4, 1),
+        ];
+        let mut game = game(&blocks, &[Position(2, 0), Position(3, 0)]);
+        let mut random = Random::new(1);
+        let moves = BTreeMap::from([
+            (PlayerId(0), ClientMessage::PlaceBomb),
+            (PlayerId(1), ClientMessage::PlaceBomb),
+        ]);
+        game.play_turn(&moves, &mut random);
+        game.play_turn(&BTreeMap::new(), &mut random);
+        let events = events(game.play_turn(&BTreeMap::new(), &mut random));
+        assert_eq!(
+            events[..2],
+            [
+                Event::BombExploded {
+                    id: BombId(0),
+                    killed: vec![PlayerId(0), PlayerId(1)],
+                    blocks_destroyed: BTreeSet::from([Position(2, 1)]),
+                },
+                Event::BombExploded {
+                    id: BombId(1),
+                    killed: vec![PlayerId(0), PlayerId(1)],
+                    blocks_destroyed: BTreeSet::from([Position(3, 1)]),
+                },
+            ]
+        );
+        assert!(matches!(
+            events[2..],
+            [
+                Event::PlayerMoved {
+                    id: PlayerId(0),
+                    ..
+                },
+                Event::PlayerMoved {
+                    id: PlayerId(1),
+                    ..
+                }
+            ]
+        ));
+        assert_eq!(
+            game.blocks(),
+            &HashSet::from([Position(1, 1), Position(4, 1)])
+        );
+        assert_eq!(game.scores()[&PlayerId(1)], Score { deaths: 1 });
+    }
+
+    #[test]
+    fn explosion_on_block_does_not_spread() {
+        let board = Board::new(5, 5).unwrap();
+        let blocks = HashSet::from([Position(2, 2)]);
+        assert_eq!(
+            explosion(Position(2, 2), 3, &board, &blocks),
+            BTreeSet::from([Position(2, 2)])
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! Movement on the board. The origin is the lower-left corner (README 2.5),
+//! so `Up` increases `y` and `Right` increases `x`.
+
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+
+use crate::{Board, Direction, Position};
+
+impl Direction {
+    /// All directions, in the order of their protocol tags.
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ];
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Right => Direction::Left,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "up" => Ok(Direction::Up),
+            "right" => Ok(Direction::Right),
+            "down" => Ok(Direction::Down),
+            "left" => Ok(Direction::Left),
+            _ => Err(format!("Unknown direction: {}", s)),
+        }
+    }
+}
+
+impl Position {
+    pub fn x(self) -> u16 {
+        self.0
+    }
+
+    pub fn y(self) -> u16 {
+        self.1
+    }
+
+    /// The adjacent position in the given direction, if it is on the board.
+    pub fn step(self, direction: Direction, board: &Board) -> Option<Position> {
+        let Position(x, y) = self;
+        let (x, y) = match direction {
+            Direction::Up => (Some(x), y.checked_add(1)),
+            Direction::Right => (x.checked_add(1), Some(y)),
+            Direction::Down => (Some(x), y.checked_sub(1)),
+            Direction::Left => (x.checked_sub(1), Some(y)),
+        };
+        board.position(x?, y?)
+    }
+
+    pub fn neighbours(self, board: &Board) -> impl Iterator<Item = Position> + '_ {
+        Direction::ALL
+            .into_iter()
+            .filter_map(move |direction| self.step(direction, board))
+    }
+
+    pub fn manhattan_distance(self, other: Position) -> u32 {
+        let dx = (self.0 as i32 - other.0 as i32).unsigned_abs();
+        let dy = (self.1 as i32 - other.1 as i32).unsigned_abs();
+        dx + dy
+    }
+
+    /// Positions at distance `1..=length` in the given direction, nearest first,
+    /// cut off at the edge of the board.
+    pub fn ray(self, direction: Direction, length: u16, board: &Board) -> Ray {
+        Ray {
+            position: self,
+            direction,
+            remaining: length,
+            board: *board,
+        }
+    }
+
+    /// Steps of a shortest walk to `target` which never enters a `blocked` position,
+    /// or `None` if there is none. The starting position may be blocked itself,
+    /// since a block can be placed under a robot.
+    pub fn path_to(
+        self,
+        target: Position,
+        board: &Board,
+        blocked: impl Fn(Position) -> bool,
+    ) -> Option<Vec<Direction>> {
+        if !board.contains(self) || !board.contains(target) || blocked(target) {
+            return None;
+        }
+        // Breadth-first, so ties are broken by the order of `Direction::ALL`.
+        let mut came_from = HashMap::from([(self, None)]);
+        let mut queue = VecDeque::from([self]);
+        while let Some(position) = queue.pop_front() {
+            if position == target {
+                break;
+            }
+            for direction in Direction::ALL {
+                let next = match position.step(direction, board) {
+                    Some(next) if !blocked(next) => next,
+                    _ => continue,
+                };
+                came_from.entry(next).or_insert_with(|| {
+                    queue.push_back(next);
+                    Some((position, direction))
+                });
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut position = target;
+        while let Some((previous, direction)) = *came_from.get(&position)? {
+            path.push(direction);
+            position = previous;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// The four arms of a cross centred here, in the order of [`Direction::ALL`].
+    /// The centre itself is not part of any arm.
+    pub fn cross(self, radius: u16, board: &Board) -> Cross {
+        Cross {
+            center: self,
+            radius,
+            board: *board,
+            directions: Direction::ALL.into_iter(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ray {
+    position: Position,
+    direction: Direction,
+    remaining: u16,
+    board: Board,
+}
+
+impl Ray {
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+}
+
+impl Iterator for Ray {
+    type Item = Position;
+
+    fn next(&mut self) -> Option<Position> {
+        if self.remaining == 0 {
+            return None;
+        }
+        match self.position.step(self.direction, &self.board) {
+            Some(position) => {
+                self.position = position;
+                self.remaining -= 1;
+                Some(position)
+            }
+            None => {
+                self.remaining = 0;
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cross {
+    center: Position,
+    radius: u16,
+    board: Board,
+    directions: std::array::IntoIter<Direction, 4>,
+}
+
+impl Iterator for Cross {
+    type Item = Ray;
+
+    fn next(&mut self) -> Option<Ray> {
+        let direction = self.directions.next()?;
+        Some(self.center.ray(direction, self.radius, &self.board))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn board(size_x: u16, size_y: u16) -> Board {
+        Board::new(size_x, size_y).unwrap()
+    }
+
+    #[test]
+    fn opposite_is_an_involution() {
+        for direction in Direction::ALL {
+            assert_ne!(direction.opposite(), direction);
+            assert_eq!(direction.opposite().opposite(), direction);
+        }
+    }
+
+    #[test]
+    fn step_from_origin() {
+        let board = board(3, 3);
+        let origin = Position(0, 0);
+        assert_eq!(origin.step(Direction::Up, &board), Some(Position(0, 1)));
+        assert_eq!(origin.step(Direction::Right, &board), Some(Position(1, 0)));
+        assert_eq!(origin.step(Direction::Down, &board), None);
+        assert_eq!(origin.step(Direction::Left, &board), None);
+    }
+
+    #[test]
+    fn step_from_top_right_corner() {
+        let board = board(3, 3);
+        let corner = Position(2, 2);
+        assert_eq!(corner.step(Direction::Up, &board), None);
+        assert_eq!(corner.step(Direction::Right, &board), None);
+        assert_eq!(corner.step(Direction::Down, &board), Some(Position(2, 1)));
+        assert_eq!(corner.step(Direction::Left, &board), Some(Position(1, 2)));
+    }
+
+    #[test]
+    fn step_on_single_cell_board() {
+        let board = board(1, 1);
+        for direction in Direction::ALL {
+            assert_eq!(Position(0, 0).step(direction, &board), None);
+        }
+        assert_eq!(Position(0, 0).neighbours(&board).count(), 0);
+    }
+
+    #[test]
+    fn step_at_largest_board() {
+        let board = board(u16::MAX, u16::MAX);
+        let corner = Position(u16::MAX - 1, u16::MAX - 1);
+        assert_eq!(corner.step(Direction::Up, &board), None);
+        assert_eq!(corner.step(Direction::Right, &board), None);
+    }
+
+    #[test]
+    fn step_everywhere() {
+        let board = board(4, 3);
+        for position in board.positions() {
+            for direction in Direction::ALL {
+                let on_edge = match direction {
+                    Direction::Up => position.1 == 2,
+                    Direction::Right => position.0 == 3,
+                    Direction::Down => position.1 == 0,
+                    Direction::Left => position.0 == 0,
+                };
+                match position.step(direction, &board) {
+                    Some(next) => {
+                        assert!(!on_edge, "{:?} {:?}", position, direction);
+                        assert_eq!(position.manhattan_distance(next), 1);
+                        assert_eq!(next.step(direction.opposite(), &board), Some(position));
+                    }
+                    None => assert!(on_edge, "{:?} {:?}", position, direction),
+                }
+            }
+            let expected = Direction::ALL
+                .into_iter()
+                .filter(|&direction| position.step(direction, &board).is_some())
+                .count();
+            assert_eq!(position.neighbours(&board).count(), expected);
+        }
+    }
+
+    #[test]
+    fn manhattan_distance() {
+        assert_eq!(Position(0, 0).manhattan_distance(Position(0, 0)), 0);
+        assert_eq!(Position(1, 5).manhattan_distance(Position(4, 1)), 7);
+        assert_eq!(
+            Position(0, u16::MAX).manhattan_distance(Position(u16::MAX, 0)),
+            2 * u16::MAX as u32
+        );
+    }
+
+    #[test]
+    fn path_around_blocks() {
+        // .....
+        // .###.
+        // .#T..
+        // S#...
+        let board = board(5, 4);
+        let blocks = [(1, 0), (1, 1), (1, 2), (2, 2), (3, 2)]
+            .map(|(x, y)| Position(x, y))
+            .to_vec();
+        let path = Position(0, 0)
+            .path_to(Position(2, 1), &board, |p| blocks.contains(&p))
+            .unwrap();
+        assert_eq!(path.len(), 11);
+        let end = path.iter().fold(Position(0, 0), |position, &direction| {
+            let next = position.step(direction, &board).unwrap();
+            assert!(!blocks.contains(&next));
+            next
+        });
+        assert_eq!(end, Position(2, 1));
+
+        assert_eq!(
+            Position(3, 3).path_to(Position(3, 3), &board, |_| true),
+            None
+        );
+        assert_eq!(
+            Position(3, 3).path_to(Position(3, 3), &board, |_| false),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn no_path_to_enclosed_cells() {
+        let board = board(3, 3);
+        let walls = [Position(1, 0), Position(1, 1), Position(1, 2)];
+        assert_eq!(
+            Position(0, 0).path_to(Position(2, 2), &board, |p| walls.contains(&p)),
+            None
+        );
+        assert_eq!(
+            Position(0, 0).path_to(Position(3, 0), &board, |_| false),
+            None
+        );
+        // Leaving a blocked cell is allowed.
+        assert_eq!(
+            Position(0, 0).path_to(Position(0, 1), &board, |p| p == Position(0, 0)),
+            Some(vec![Direction::Up])
+        );
+    }
+
+    #[test]
+    fn cross_from_origin() {
+        let board = board(5, 5);
+        let arms = Position(0, 0)
+            .cross(2, &board)
+            .map(|ray| (ray.direction(), ray.collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            arms,
+            vec![
+                (Direction::Up, vec![Position(0, 1), Position(0, 2)]),
+                (Direction::Right, vec![Position(1, 0), Position(2, 0)]),
+                (Direction::Down, vec![]),
+                (Direction::Left, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn cross_is_cut_at_edges() {
+        let board = board(3, 2);
+        let arms = Position(1, 1)
+            .cross(10, &board)
+            .map(|ray| ray.collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            arms,
+            vec![
+                vec![],
+                vec![Position(2, 1)],
+                vec![Position(1, 0)],
+                vec![Position(0, 1)],
+            ]
+        );
+    }
+
+    #[test]
+    fn cross_with_zero_radius() {
+        let board = board(3, 3);
+        assert!(Position(1, 1)
+            .cross(0, &board)
+            .all(|mut ray| ray.next().is_none()));
+    }
+}
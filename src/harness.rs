@@ -0,0 +1,446 @@
+//! Building blocks for tools which drive a real server or client over localhost:
+//! launching the program, finding free ports, exchanging protocol messages with timeouts
+//! and running named scenarios.
+
+use std::io::{self, Read, Write};
+use std::net::{Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::serialize::deserializer::{self, Deserializer};
+use crate::serialize::read::IoRead;
+use crate::serialize::{serializer, DeserError};
+use crate::MAX_UDP_LENGTH;
+
+/// How often [`Process::wait_timeout`] and [`connect`] poll.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Error)]
+pub enum HarnessError {
+    #[error("Timed out")]
+    Timeout,
+    #[error("Connection closed")]
+    Closed,
+    #[error("Invalid message: {0}")]
+    Invalid(DeserError),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+impl From<DeserError> for HarnessError {
+    fn from(error: DeserError) -> Self {
+        match error {
+            DeserError::Io(e) => match e.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => HarnessError::Timeout,
+                io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted => HarnessError::Closed,
+                _ => HarnessError::Io(e),
+            },
+            other => HarnessError::Invalid(other),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, HarnessError>;
+
+/// A program under test. It is killed when dropped.
+pub struct Process {
+    child: Child,
+}
+
+impl Process {
+    /// Starts the program. Its output is discarded unless `verbose` is set.
+    pub fn spawn(program: &Path, args: &[String], verbose: bool) -> io::Result<Process> {
+        let output = || {
+            if verbose {
+                Stdio::inherit()
+            } else {
+                Stdio::null()
+            }
+        };
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(output())
+            .stderr(output())
+            .spawn()?;
+        Ok(Process { child })
+    }
+
+    /// The exit status, if the program has already finished.
+    pub fn exit_status(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.child.try_wait()
+    }
+
+    /// Waits for the program to finish, for at most `timeout`.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                return Ok(Some(status));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A TCP port which was free a moment ago. Another process may take it in the meantime,
+/// but that is unlikely enough for tests.
+pub fn free_tcp_port() -> io::Result<u16> {
+    let listener = TcpListener::bind((Ipv6Addr::UNSPECIFIED, 0))
+        .or_else(|_| TcpListener::bind(("0.0.0.0", 0)))?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// A UDP port which was free a moment ago, see [`free_tcp_port`].
+pub fn free_udp_port() -> io::Result<u16> {
+    let socket =
+        UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0)).or_else(|_| UdpSocket::bind(("0.0.0.0", 0)))?;
+    Ok(socket.local_addr()?.port())
+}
+
+/// Connects to the address, retrying until `timeout` passes, since a freshly started
+/// server may not be listening yet.
+pub fn connect(address: impl ToSocketAddrs, timeout: Duration) -> Result<Connection> {
+    let addresses = address.to_socket_addrs()?.collect::<Vec<_>>();
+    let deadline = Instant::now() + timeout;
+    loop {
+        for address in &addresses {
+            if let Ok(stream) = TcpStream::connect_timeout(address, timeout) {
+                return Ok(Connection::new(stream)?);
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err(HarnessError::Timeout);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// A TCP connection speaking the binary protocol.
+pub struct Connection {
+    writer: TcpStream,
+    reader: Deserializer<IoRead<TcpStream>>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Connection> {
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            writer: stream.try_clone()?,
+            reader: Deserializer::new(stream),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.writer.local_addr()
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.writer.peer_addr()
+    }
+
+    pub fn send<T: Serialize>(&mut self, message: &T) -> Result<()> {
+        self.send_bytes(&serializer::to_bytes(message))
+    }
+
+    /// Sends raw bytes, which do not have to be a valid message.
+    pub fn send_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes)?;
+        Ok(())
+    }
+
+    /// Closes the sending half, so that the peer sees the end of the stream
+    /// while it can still write to us.
+    pub fn shutdown_write(&mut self) -> Result<()> {
+        self.writer.shutdown(Shutdown::Write)?;
+        Ok(())
+    }
+
+    /// Waits for the next message for at most `timeout`.
+    ///
+    /// A timeout in the middle of a message leaves the stream out of sync,
+    /// so the connection should not be used for anything else afterwards.
+    pub fn receive<T: DeserializeOwned>(&mut self, timeout: Duration) -> Result<T> {
+        self.writer.set_read_timeout(Some(timeout))?;
+        Ok(self.reader.deserialize()?)
+    }
+
+    /// Whether the peer sends something, or closes the connection, within `timeout`.
+    /// Nothing is consumed, so unlike [`Connection::receive`] this can time out safely.
+    pub fn readable(&mut self, timeout: Duration) -> Result<bool> {
+        self.writer.set_read_timeout(Some(timeout))?;
+        match self.writer.peek(&mut [0u8]) {
+            Ok(_) => Ok(true),
+            Err(e) => match e.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => Ok(false),
+                _ => Err(HarnessError::Io(e)),
+            },
+        }
+    }
+
+    /// Receives messages until `accept` returns `Some`, for at most `timeout` in total.
+    /// The skipped messages are returned too, in order.
+    pub fn receive_until<T: DeserializeOwned, U>(
+        &mut self,
+        timeout: Duration,
+        mut accept: impl FnMut(&T) -> Option<U>,
+    ) -> Result<(Vec<T>, U)> {
+        let deadline = Instant::now() + timeout;
+        let mut skipped = Vec::new();
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(HarnessError::Timeout);
+            }
+            let message = self.receive(left)?;
+            match accept(&message) {
+                Some(result) => return Ok((skipped, result)),
+                None => skipped.push(message),
+            }
+        }
+    }
+
+    /// Whether the peer closes the connection within `timeout`. Anything it sends
+    /// in the meantime is discarded.
+    pub fn closed_within(&mut self, timeout: Duration) -> io::Result<bool> {
+        let deadline = Instant::now() + timeout;
+        let mut buffer = [0u8; 4096];
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(false);
+            }
+            self.writer.set_read_timeout(Some(left))?;
+            match self.writer.read(&mut buffer) {
+                Ok(0) => return Ok(true),
+                Ok(_) => continue,
+                Err(e) => match e.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => return Ok(false),
+                    io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted => {
+                        return Ok(true)
+                    }
+                    io::ErrorKind::Interrupted => continue,
+                    _ => return Err(e),
+                },
+            }
+        }
+    }
+}
+
+/// A UDP socket speaking the binary protocol, e.g. a fake GUI.
+pub struct Datagrams {
+    socket: UdpSocket,
+}
+
+impl Datagrams {
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Datagrams> {
+        Ok(Datagrams {
+            socket: UdpSocket::bind(address)?,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn send_to<T: Serialize>(&self, message: &T, address: SocketAddr) -> Result<()> {
+        self.send_bytes_to(&serializer::to_bytes(message), address)
+    }
+
+    /// Sends raw bytes, which do not have to be a valid message.
+    pub fn send_bytes_to(&self, bytes: &[u8], address: SocketAddr) -> Result<()> {
+        self.socket.send_to(bytes, address)?;
+        Ok(())
+    }
+
+    /// Waits for the next datagram for at most `timeout`.
+    pub fn receive<T: DeserializeOwned>(&self, timeout: Duration) -> Result<(T, SocketAddr)> {
+        self.socket.set_read_timeout(Some(timeout))?;
+        let mut buffer = vec![0u8; MAX_UDP_LENGTH];
+        let (length, address) = self.socket.recv_from(&mut buffer).map_err(DeserError::Io)?;
+        Ok((deserializer::from_bytes(&buffer[..length])?, address))
+    }
+}
+
+/// The result of a scenario, with a diagnostic if it failed.
+pub type Outcome = std::result::Result<(), String>;
+
+/// A named check of a program under test, run by [`run_scenarios`].
+pub struct Scenario<C> {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub run: fn(&C) -> Outcome,
+}
+
+/// Lists the scenarios if `list` is set. Otherwise runs the ones named in `names`, or all
+/// of them if there are none, and prints PASS or FAIL for each. `context` is only created
+/// when something is run.
+///
+/// Returns the exit code: 1 if a scenario failed, 2 if a name is unknown.
+pub fn run_scenarios<C>(
+    scenarios: &[Scenario<C>],
+    list: bool,
+    names: &[String],
+    context: impl FnOnce() -> C,
+) -> i32 {
+    if list {
+        let width = scenarios.iter().map(|scenario| scenario.name.len()).max();
+        for scenario in scenarios {
+            println!(
+                "{:width$}  {}",
+                scenario.name,
+                scenario.description,
+                width = width.unwrap_or(0)
+            );
+        }
+        return 0;
+    }
+
+    let mut selected = Vec::new();
+    for name in names {
+        match scenarios.iter().find(|scenario| scenario.name == name) {
+            Some(scenario) => selected.push(scenario),
+            None => {
+                eprintln!("Unknown scenario {} (see --list)", name);
+                return 2;
+            }
+        }
+    }
+    if names.is_empty() {
+        selected = scenarios.iter().collect();
+    }
+
+    let context = context();
+    let mut failed = 0;
+    for scenario in &selected {
+        match (scenario.run)(&context) {
+            Ok(()) => println!("PASS {}", scenario.name),
+            Err(diagnostic) => {
+                failed += 1;
+                println!("FAIL {}: {}", scenario.name, diagnostic);
+            }
+        }
+    }
+    println!("{} passed, {} failed", selected.len() - failed, failed);
+    if failed > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ClientMessage, Direction, InputMessage};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn messages_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut client = connect(address, TIMEOUT).unwrap();
+        let mut server = Connection::new(listener.accept().unwrap().0).unwrap();
+
+        let message = ClientMessage::Move {
+            direction: Direction::Left,
+        };
+        assert!(!server.readable(Duration::from_millis(50)).unwrap());
+        client.send(&message).unwrap();
+        assert!(server.readable(TIMEOUT).unwrap());
+        assert_eq!(server.receive::<ClientMessage>(TIMEOUT).unwrap(), message);
+
+        assert!(matches!(
+            server.receive::<ClientMessage>(Duration::from_millis(50)),
+            Err(HarnessError::Timeout)
+        ));
+
+        drop(client);
+        assert!(server.closed_within(TIMEOUT).unwrap());
+    }
+
+    #[test]
+    fn receive_until_skips_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = connect(listener.local_addr().unwrap(), TIMEOUT).unwrap();
+        let mut server = Connection::new(listener.accept().unwrap().0).unwrap();
+
+        client.send(&ClientMessage::PlaceBlock).unwrap();
+        client.send(&ClientMessage::PlaceBomb).unwrap();
+        let (skipped, ()) = server
+            .receive_until(TIMEOUT, |message: &ClientMessage| {
+                (message == &ClientMessage::PlaceBomb).then_some(())
+            })
+            .unwrap();
+        assert_eq!(skipped, vec![ClientMessage::PlaceBlock]);
+    }
+
+    #[test]
+    fn invalid_message() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = connect(listener.local_addr().unwrap(), TIMEOUT).unwrap();
+        let mut server = Connection::new(listener.accept().unwrap().0).unwrap();
+
+        client.send_bytes(&[42]).unwrap();
+        assert!(matches!(
+            server.receive::<ClientMessage>(TIMEOUT),
+            Err(HarnessError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn datagrams() {
+        let gui = Datagrams::bind("127.0.0.1:0").unwrap();
+        let client = Datagrams::bind("127.0.0.1:0").unwrap();
+        gui.send_to(&InputMessage::PlaceBomb, client.local_addr().unwrap())
+            .unwrap();
+        let (message, from) = client.receive::<InputMessage>(TIMEOUT).unwrap();
+        assert_eq!(message, InputMessage::PlaceBomb);
+        assert_eq!(from, gui.local_addr().unwrap());
+    }
+
+    #[test]
+    fn scenarios() {
+        let scenarios = [
+            Scenario {
+                name: "pass",
+                description: "always passes",
+                run: |_: &()| Ok(()),
+            },
+            Scenario {
+                name: "fail",
+                description: "always fails",
+                run: |_| Err("failed".to_string()),
+            },
+        ];
+        assert_eq!(run_scenarios(&scenarios, true, &[], || panic!()), 0);
+        assert_eq!(
+            run_scenarios(&scenarios, false, &["pass".to_string()], || ()),
+            0
+        );
+        assert_eq!(run_scenarios(&scenarios, false, &[], || ()), 1);
+        assert_eq!(
+            run_scenarios(&scenarios, false, &["other".to_string()], || panic!()),
+            2
+        );
+    }
+}
@@ -1,11 +1,30 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashSet};
-use std::net::SocketAddr;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
+pub mod ascii;
+pub mod board;
+pub mod borrowed;
+#[cfg(feature = "tools")]
+pub mod client;
+pub mod diff;
+pub mod display;
+pub mod game;
+pub mod geometry;
+#[cfg(feature = "tools")]
+pub mod harness;
+#[cfg(feature = "tools")]
+pub mod mutation;
+pub mod names;
 pub mod random;
 pub mod serialize;
+pub mod simulation;
+pub mod trace;
+
+pub use board::Board;
+pub use names::{PlayerAddress, PlayerName, ServerName};
 
 pub const MAX_UDP_LENGTH: usize = 65_535;
+pub const MAX_STRING_LENGTH: usize = 255;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum InputMessage {
@@ -18,7 +37,7 @@ pub enum InputMessage {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DisplayMessage {
     Lobby {
-        server_name: String,
+        server_name: ServerName,
         players_count: u8,
         size_x: u16,
         size_y: u16,
@@ -28,7 +47,7 @@ pub enum DisplayMessage {
         players: BTreeMap<PlayerId, Player>,
     },
     Game {
-        server_name: String,
+        server_name: ServerName,
         size_x: u16,
         size_y: u16,
         game_length: u16,
@@ -44,7 +63,7 @@ pub enum DisplayMessage {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ClientMessage {
-    Join { name: String },
+    Join { name: PlayerName },
     PlaceBomb,
     PlaceBlock,
     Move { direction: Direction },
@@ -77,7 +96,7 @@ pub struct Bomb {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ServerMessage {
     Hello {
-        server_name: String,
+        server_name: ServerName,
         players_count: u8,
         size_x: u16,
         size_y: u16,
@@ -108,11 +127,13 @@ pub struct Score {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Player {
-    pub name: String,
-    pub socket_addr: SocketAddr,
+    pub name: PlayerName,
+    pub address: PlayerAddress,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default, Ord, PartialOrd,
+)]
 pub struct Position(pub u16, pub u16);
 
 #[derive(
@@ -134,7 +155,7 @@ pub enum Event {
     BombExploded {
         id: BombId,
         killed: Vec<PlayerId>,
-        blocks_destroyed: HashSet<Position>,
+        blocks_destroyed: BTreeSet<Position>,
     },
     PlayerMoved {
         id: PlayerId,
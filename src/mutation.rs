@@ -0,0 +1,177 @@
+//! Malformed `ClientMessage` streams for checking that servers disconnect misbehaving clients
+//! (README 2.11).
+
+use serde::Deserialize;
+
+use crate::random::Random;
+use crate::serialize::deserializer::Deserializer;
+use crate::serialize::serializer::to_bytes;
+use crate::serialize::DeserError;
+use crate::{ClientMessage, Direction};
+
+/// What a server can make of a stream of bytes sent by a client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validity {
+    /// Every byte belongs to a correct message.
+    Valid,
+    /// The stream ends in the middle of a message. The server only finds out
+    /// once the client closes its side of the connection.
+    Incomplete,
+    /// Some message is malformed, so the client must be disconnected.
+    Invalid,
+}
+
+/// Classifies a stream of client messages the way a server reading it would.
+pub fn classify(mut bytes: &[u8]) -> Validity {
+    while !bytes.is_empty() {
+        let mut deserializer = Deserializer::from_bytes(bytes);
+        match ClientMessage::deserialize(&mut deserializer) {
+            Ok(_) => bytes = deserializer.remaining(),
+            Err(DeserError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Validity::Incomplete
+            }
+            Err(_) => return Validity::Invalid,
+        }
+    }
+    Validity::Valid
+}
+
+/// Bytes to send over a single connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MutatedStream {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+impl MutatedStream {
+    fn new(name: &str, bytes: Vec<u8>) -> MutatedStream {
+        MutatedStream {
+            name: name.to_string(),
+            bytes,
+        }
+    }
+
+    pub fn validity(&self) -> Validity {
+        classify(&self.bytes)
+    }
+
+    /// Whether the server has to close the connection. Incomplete streams count too,
+    /// since the sender closes its side of the connection after them.
+    pub fn expect_disconnect(&self) -> bool {
+        self.validity() != Validity::Valid
+    }
+}
+
+fn join(name: &str) -> Vec<u8> {
+    to_bytes(ClientMessage::Join {
+        name: name.parse().expect("name is short"),
+    })
+}
+
+/// Hand-picked malformed streams, plus one valid stream as a control.
+pub fn catalogue() -> Vec<MutatedStream> {
+    let moves = [
+        to_bytes(ClientMessage::PlaceBomb),
+        to_bytes(ClientMessage::PlaceBlock),
+        to_bytes(ClientMessage::Move {
+            direction: Direction::Left,
+        }),
+    ]
+    .concat();
+    vec![
+        MutatedStream::new("valid-control", [moves.clone(), join("name")].concat()),
+        MutatedStream::new("invalid-tag", vec![4]),
+        MutatedStream::new("invalid-tag-255", vec![255]),
+        MutatedStream::new("invalid-direction", vec![3, 4]),
+        MutatedStream::new("valid-then-invalid-tag", [moves, vec![9]].concat()),
+        MutatedStream::new("bad-utf8", vec![0, 2, 0xff, 0xfe]),
+        MutatedStream::new("overlong-utf8", vec![0, 2, 0xc0, 0xaf]),
+        MutatedStream::new("surrogate-utf8", vec![0, 3, 0xed, 0xa0, 0x80]),
+        MutatedStream::new("truncated-utf8", vec![0, 1, 0xc3]),
+        MutatedStream::new("truncated-string", vec![0, 10, b'a', b'b', b'c']),
+        MutatedStream::new("truncated-move", vec![3]),
+        // Strings have a one-byte length, so 255 is the largest one a client can declare.
+        MutatedStream::new("huge-length", [vec![0, 255], vec![b'a'; 100]].concat()),
+        MutatedStream::new(
+            "http-request",
+            b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n".to_vec(),
+        ),
+    ]
+}
+
+/// A few valid messages with random bytes changed, inserted or cut off.
+/// The result may happen to be valid, see [`MutatedStream::validity`].
+pub fn random_stream(random: &mut Random, index: usize) -> MutatedStream {
+    let mut bytes = Vec::new();
+    for _ in 0..random.range_u16(1, 6) {
+        let message = match random.range_u16(0, 4) {
+            0 => {
+                let length = random.range_u16(0, 20) as usize;
+                let name = (0..length)
+                    .map(|_| char::from(b'a' + random.range_u16(0, 26) as u8))
+                    .collect::<String>();
+                ClientMessage::Join {
+                    name: name.parse().expect("name is short"),
+                }
+            }
+            1 => ClientMessage::PlaceBomb,
+            2 => ClientMessage::PlaceBlock,
+            _ => ClientMessage::Move {
+                direction: Direction::ALL[random.range_u16(0, 4) as usize],
+            },
+        };
+        bytes.extend(to_bytes(message));
+    }
+
+    for _ in 0..random.range_u16(1, 4) {
+        let position = random.range_u16(0, bytes.len() as u16) as usize;
+        let byte = random.next_u32() as u8;
+        match random.range_u16(0, 3) {
+            0 => bytes[position] = byte,
+            1 => bytes.insert(position, byte),
+            _ => bytes.truncate(position.max(1)),
+        }
+    }
+    MutatedStream::new(&format!("random-{}", index), bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn classification() {
+        assert_eq!(classify(&[]), Validity::Valid);
+        assert_eq!(classify(&[1, 2, 3, 0]), Validity::Valid);
+        assert_eq!(classify(&[0, 3, b'a']), Validity::Incomplete);
+        assert_eq!(classify(&[1, 3]), Validity::Incomplete);
+        assert_eq!(classify(&[1, 7]), Validity::Invalid);
+        assert_eq!(classify(&[0, 1, 0xff]), Validity::Invalid);
+    }
+
+    #[test]
+    fn catalogue_is_malformed_except_the_control() {
+        for stream in catalogue() {
+            let expected = stream.name == "valid-control";
+            assert_eq!(
+                stream.validity() == Validity::Valid,
+                expected,
+                "{}",
+                stream.name
+            );
+        }
+    }
+
+    #[test]
+    fn random_streams_are_deterministic() {
+        let streams = |seed| {
+            let mut random = Random::new(seed);
+            (0..20)
+                .map(|index| random_stream(&mut random, index))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(streams(5), streams(5));
+        assert_ne!(streams(5), streams(6));
+        assert!(streams(5).iter().all(|stream| !stream.bytes.is_empty()));
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::MAX_STRING_LENGTH;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(
+    "String is {0} bytes long, but at most {} are allowed",
+    MAX_STRING_LENGTH
+)]
+pub struct NameTooLong(pub usize);
+
+/// Defines a `String` newtype which can only hold strings encodable in the protocol,
+/// i.e. shorter than 256 bytes.
+macro_rules! protocol_string {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new(value: impl Into<String>) -> Result<Self, NameTooLong> {
+                let value = value.into();
+                if value.len() > MAX_STRING_LENGTH {
+                    Err(NameTooLong(value.len()))
+                } else {
+                    Ok($name(value))
+                }
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            pub fn into_string(self) -> String {
+                self.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = NameTooLong;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $name::new(s)
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = NameTooLong;
+
+            fn try_from(value: String) -> Result<Self, Self::Error> {
+                $name::new(value)
+            }
+        }
+
+        impl TryFrom<&str> for $name {
+            type Error = NameTooLong;
+
+            fn try_from(value: &str) -> Result<Self, Self::Error> {
+                $name::new(value)
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> String {
+                value.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                $name::new(value).map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+protocol_string!(
+    /// Name of a player, as sent in `Join` and `Player`.
+    PlayerName
+);
+
+protocol_string!(
+    /// Name of a server, as sent in `Hello` and forwarded to the GUI.
+    ServerName
+);
+
+protocol_string!(
+    /// Address of a player exactly as the server reported it.
+    ///
+    /// The protocol does not constrain its format, so it is kept verbatim
+    /// and only parsed on demand.
+    PlayerAddress
+);
+
+impl PlayerAddress {
+    /// Returns `None` for addresses which are not a literal IP and port,
+    /// e.g. `localhost:1234` or `[fe80::1%eth0]:1234`.
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        self.0.parse().ok()
+    }
+}
+
+impl From<SocketAddr> for PlayerAddress {
+    fn from(address: SocketAddr) -> PlayerAddress {
+        // The longest possible rendering, a scoped IPv6 address with a port, fits easily.
+        PlayerAddress(address.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accepts_names_up_to_limit() {
+        let name = "ż".repeat(127) + "a";
+        assert_eq!(name.len(), 255);
+        assert_eq!(PlayerName::new(name.clone()).unwrap(), name.as_str());
+        assert_eq!(ServerName::new("").unwrap(), "");
+    }
+
+    #[test]
+    fn rejects_names_over_limit() {
+        let name = "ż".repeat(128);
+        assert_eq!(PlayerName::new(name.clone()), Err(NameTooLong(256)));
+        assert_eq!(name.parse::<ServerName>(), Err(NameTooLong(256)));
+    }
+
+    #[test]
+    fn player_address_keeps_raw_string() {
+        let address = PlayerAddress::new("localhost:1234").unwrap();
+        assert_eq!(address, "localhost:1234");
+        assert_eq!(address.socket_addr(), None);
+
+        let address = PlayerAddress::new("[fe80::1%eth0]:1234").unwrap();
+        assert_eq!(address, "[fe80::1%eth0]:1234");
+
+        let socket_addr: SocketAddr = "[::1]:2022".parse().unwrap();
+        let address = PlayerAddress::from(socket_addr);
+        assert_eq!(address, "[::1]:2022");
+        assert_eq!(address.socket_addr(), Some(socket_addr));
+    }
+
+    #[test]
+    fn deserialization_validates() {
+        let name = serde_json::to_string(&"a".repeat(300)).unwrap();
+        assert!(serde_json::from_str::<PlayerName>(&name).is_err());
+        let name = serde_json::to_string(&"a".repeat(30)).unwrap();
+        assert!(serde_json::from_str::<PlayerName>(&name).is_ok());
+    }
+}
@@ -1,34 +1,170 @@
+use std::collections::HashMap;
+
+use rand::{Error, RngCore, SeedableRng};
+
+/// The `minstd_rand` generator required by the spec (README 2.4).
+///
+/// Seeds divisible by 2147483647 (that is 0, 2147483647 and 4294967294) are degenerate:
+/// the generator then returns 0 forever. [`Random::new`] accepts them anyway, because the
+/// spec does too, but [`Random::checked_new`] and [`SeedableRng::from_seed`] do not.
 #[derive(Debug, Clone, Default)]
 pub struct Random {
-    seed: u32,
+    state: u32,
 }
 
+const MULTIPLIER: u64 = 48271;
+const MODULUS: u64 = 2147483647;
+
 impl Random {
     pub fn new(seed: u32) -> Random {
-        Random { seed }
+        Random { state: seed }
+    }
+
+    /// Like [`Random::new`], but returns `None` for degenerate seeds.
+    pub fn checked_new(seed: u32) -> Option<Random> {
+        (!Random::is_degenerate(seed)).then(|| Random::new(seed))
+    }
+
+    pub fn is_degenerate(seed: u32) -> bool {
+        matches!(seed, 0 | 2147483647 | 4294967294)
+    }
+
+    /// The last value returned by [`Random::next_u32`], or the seed if there was none.
+    pub fn state(&self) -> u32 {
+        self.state
     }
 
+    /// Resumes a generator whose [`Random::state`] was saved earlier.
+    pub fn from_state(state: u32) -> Random {
+        Random { state }
+    }
+
+    /// Returns the next value of the sequence, which always lies in `1..2147483647`
+    /// (unless the seed was degenerate).
     pub fn next_u32(&mut self) -> u32 {
-        let seed = self.seed;
-        let seed_u64 = seed as u64;
-        let value = (seed_u64 * 48271) % 2147483647;
-        self.seed = value as u32;
-        self.seed
+        let value = (self.state as u64 * MULTIPLIER) % MODULUS;
+        self.state = value as u32;
+        self.state
+    }
+
+    /// Skips `n` values, as if [`Random::next_u32`] was called `n` times, in O(log n).
+    pub fn advance(&mut self, n: u64) {
+        if n > 0 {
+            let value = (self.state as u64 * pow_mod(MULTIPLIER, n)) % MODULUS;
+            self.state = value as u32;
+        }
+    }
+
+    /// Returns the least number of draws which take a generator from state `from`
+    /// to state `to`, or `None` if `to` is never reached.
+    ///
+    /// The multiplier is a primitive root modulo 2147483647, so this is a discrete logarithm,
+    /// computed with the baby-step giant-step algorithm in about 2^16 steps.
+    pub fn draws_between(from: u32, to: u32) -> Option<u64> {
+        if from == to {
+            return Some(0);
+        }
+        // Every state is reduced by the first draw, so larger ones are never reached.
+        if to as u64 >= MODULUS {
+            return None;
+        }
+        let from = from as u64 % MODULUS;
+        let to = to as u64;
+        if from == 0 || to == 0 {
+            // Every draw from a degenerate state returns 0, and 0 is only reachable from there.
+            return (from == 0 && to == 0).then_some(1);
+        }
+
+        // Write n = i * m - j, where 1 <= i <= m and 0 <= j < m, and look for
+        // from * MULTIPLIER^(i * m) == to * MULTIPLIER^j. The first i with a match
+        // gives the least n, since the ranges of n for consecutive i do not overlap.
+        let m = ((MODULUS - 1) as f64).sqrt().ceil() as u64;
+
+        let mut baby_steps = HashMap::with_capacity(m as usize);
+        let mut value = to;
+        for j in 0..m {
+            baby_steps.insert(value, j);
+            value = value * MULTIPLIER % MODULUS;
+        }
+
+        let giant_step = pow_mod(MULTIPLIER, m);
+        let mut value = from;
+        for i in 1..=m {
+            value = value * giant_step % MODULUS;
+            if let Some(&j) = baby_steps.get(&value) {
+                return Some(i * m - j);
+            }
+        }
+        None
     }
 
+    /// Returns a value in `min..max`, or `min` if the range is empty.
+    /// Exactly one value is drawn from the generator either way.
     pub fn range_u16(&mut self, min: u16, max: u16) -> u16 {
-        let min = min as u32;
-        let max = max as u32;
-        let range = max - min;
         let value = self.next_u32();
-        let value_in_range = (value % range) + min;
-        (value_in_range % (u16::MAX as u32)) as u16
+        if max <= min {
+            return min;
+        }
+        let range = (max - min) as u32;
+        (value % range) as u16 + min
+    }
+}
+
+fn pow_mod(base: u64, mut exponent: u64) -> u64 {
+    let mut base = base % MODULUS;
+    let mut result = 1;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base % MODULUS;
+        }
+        base = base * base % MODULUS;
+        exponent >>= 1;
+    }
+    result
+}
+
+/// Draws from the spec's sequence are 31 bits wide, so each `u32` here takes the upper
+/// 16 bits of two consecutive draws. Use the inherent [`Random::next_u32`] to follow the spec.
+impl RngCore for Random {
+    fn next_u32(&mut self) -> u32 {
+        let high = Random::next_u32(self) >> 15;
+        let low = Random::next_u32(self) >> 15;
+        (high << 16) | low
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = RngCore::next_u32(self) as u64;
+        let high = RngCore::next_u32(self) as u64;
+        (high << 32) | low
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = RngCore::next_u32(self).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Random {
+    type Seed = [u8; 4];
+
+    /// Interprets the seed as a big-endian `u32`. Degenerate seeds are replaced with 1.
+    fn from_seed(seed: [u8; 4]) -> Random {
+        let seed = u32::from_be_bytes(seed);
+        Random::checked_new(seed).unwrap_or_else(|| Random::new(1))
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::Rng;
 
     #[test]
     fn seed_1234() {
@@ -40,4 +176,133 @@ mod test {
         assert_eq!(random.next_u32(), 982122076);
         assert_eq!(random.next_u32(), 165739424);
     }
+
+    #[test]
+    fn rng_core_uses_all_bits() {
+        let mut random = Random::new(1234);
+        assert_eq!(
+            RngCore::next_u32(&mut random),
+            ((59566414 >> 15) << 16) | (1997250508 >> 15)
+        );
+        assert_eq!(random.state(), 1997250508);
+
+        let mut random = Random::from_seed(1234u32.to_be_bytes());
+        assert_eq!(random.next_u32(), 59566414);
+        let values = (0..1000)
+            .map(|_| random.gen_range(0..100))
+            .collect::<Vec<u32>>();
+        assert!(values.iter().all(|value| *value < 100));
+        assert!(values.iter().any(|value| *value >= 50));
+        assert!((0..100).any(|_| RngCore::next_u32(&mut random) >> 31 == 1));
+        assert!((0..100).any(|_| random.gen::<f64>() >= 0.5));
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let mut random = Random::new(1234);
+        random.next_u32();
+        random.next_u32();
+        let mut resumed = Random::from_state(random.state());
+        assert_eq!(resumed.state(), 1997250508);
+        for _ in 0..10 {
+            assert_eq!(resumed.next_u32(), random.next_u32());
+        }
+    }
+
+    #[test]
+    fn degenerate_seeds() {
+        for seed in [0, 2147483647, 4294967294] {
+            assert!(Random::is_degenerate(seed));
+            assert!(Random::checked_new(seed).is_none());
+            assert_eq!(Random::new(seed).next_u32(), 0);
+            assert_ne!(Random::from_seed(seed.to_be_bytes()).next_u32(), 0);
+        }
+        assert!(Random::checked_new(1).is_some());
+        assert!(Random::checked_new(u32::MAX).is_some());
+    }
+
+    #[test]
+    fn advance_matches_stepwise() {
+        let mut stepwise = Random::new(1234);
+        let mut advanced = Random::new(1234);
+        advanced.advance(0);
+        assert_eq!(advanced.state(), 1234);
+        for n in [1, 2, 5, 17, 1000] {
+            for _ in 0..n {
+                stepwise.next_u32();
+            }
+            advanced.advance(n);
+            assert_eq!(advanced.state(), stepwise.state());
+        }
+
+        let mut random = Random::new(u32::MAX);
+        let expected = random.next_u32();
+        let mut advanced = Random::new(u32::MAX);
+        advanced.advance(1);
+        assert_eq!(advanced.state(), expected);
+    }
+
+    #[test]
+    fn advance_by_period() {
+        let mut random = Random::new(1234);
+        random.advance(2147483646);
+        assert_eq!(random.state(), 1234);
+        random.advance(u64::MAX);
+        let mut expected = Random::new(1234);
+        expected.advance(u64::MAX % 2147483646);
+        assert_eq!(random.state(), expected.state());
+    }
+
+    #[test]
+    fn draws_between_matches_stepwise() {
+        let mut random = Random::new(1234);
+        assert_eq!(Random::draws_between(1234, 1234), Some(0));
+        for n in 1..=20 {
+            random.next_u32();
+            assert_eq!(Random::draws_between(1234, random.state()), Some(n));
+        }
+
+        let mut random = Random::new(42);
+        random.advance(1_000_000_007);
+        assert_eq!(
+            Random::draws_between(42, random.state()),
+            Some(1_000_000_007)
+        );
+        assert_eq!(
+            Random::draws_between(random.state(), 42),
+            Some(2147483646 - 1_000_000_007)
+        );
+    }
+
+    #[test]
+    fn draws_between_degenerate_states() {
+        assert_eq!(Random::draws_between(0, 0), Some(0));
+        assert_eq!(Random::draws_between(2147483647, 0), Some(1));
+        assert_eq!(Random::draws_between(0, 1), None);
+        assert_eq!(Random::draws_between(1, 0), None);
+        assert_eq!(Random::draws_between(0, 2147483647), None);
+        assert_eq!(Random::draws_between(2147483647, 2147483647), Some(0));
+    }
+
+    #[test]
+    fn draws_between_unreachable_states() {
+        assert_eq!(Random::draws_between(1, 2147483648), None);
+        assert_eq!(Random::draws_between(1234, u32::MAX), None);
+        let mut random = Random::new(2147483648);
+        random.next_u32();
+        assert_eq!(Random::draws_between(2147483648, random.state()), Some(1));
+    }
+
+    #[test]
+    fn range_u16() {
+        let mut random = Random::new(1234);
+        assert_eq!(random.range_u16(5, 5), 5);
+        assert_eq!(random.range_u16(7, 3), 7);
+        assert_eq!(random.state(), 1997250508);
+        for _ in 0..100 {
+            let value = random.range_u16(3, 7);
+            assert!((3..7).contains(&value));
+        }
+        assert!(random.range_u16(0, u16::MAX) < u16::MAX);
+    }
 }
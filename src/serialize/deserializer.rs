@@ -1,5 +1,6 @@
-use std::io::{Cursor, Read};
+use std::io;
 
+use crate::serialize::read::{IoRead, Read, Reference, SliceRead};
 use crate::serialize::DeserError;
 use byteorder::{NetworkEndian, ReadBytesExt};
 use serde::de::{
@@ -10,13 +11,17 @@ use serde::Deserialize;
 
 use super::error::Result;
 
-pub struct Deserializer<R: Read> {
+pub struct Deserializer<R> {
     input: R,
+    scratch: Vec<u8>,
 }
 
-impl<R: Read> Deserializer<R> {
+impl<R: io::Read> Deserializer<IoRead<R>> {
     pub fn new(input: R) -> Self {
-        Deserializer { input }
+        Deserializer {
+            input: IoRead::new(input),
+            scratch: Vec::new(),
+        }
     }
 
     pub fn deserialize<T: DeserializeOwned>(&mut self) -> Result<T> {
@@ -24,12 +29,18 @@ impl<R: Read> Deserializer<R> {
     }
 }
 
-impl<'de> Deserializer<Cursor<&'de [u8]>> {
+impl<'de> Deserializer<SliceRead<'de>> {
     pub fn from_bytes(input: &'de [u8]) -> Self {
         Deserializer {
-            input: Cursor::new(input),
+            input: SliceRead::new(input),
+            scratch: Vec::new(),
         }
     }
+
+    /// Bytes which have not been deserialized yet.
+    pub fn remaining(&self) -> &'de [u8] {
+        self.input.remaining()
+    }
 }
 
 pub fn from_bytes<'de, T>(input: &'de [u8]) -> Result<T>
@@ -39,14 +50,14 @@ where
     let mut deserializer = Deserializer::from_bytes(input);
     let value = T::deserialize(&mut deserializer)?;
 
-    if deserializer.input.position() == deserializer.input.get_ref().len() as u64 {
+    if deserializer.remaining().is_empty() {
         Ok(value)
     } else {
         Err(DeserError::TrailingData)
     }
 }
 
-impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
+impl<'de, 'a, R: Read<'de>> de::Deserializer<'de> for &'a mut Deserializer<R> {
     type Error = DeserError;
 
     fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
@@ -149,11 +160,11 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
     where
         V: Visitor<'de>,
     {
-        // TODO: this can be improved in terms of borrowing
         let length = self.input.read_u8()?;
-        let mut buf = vec![0; length as usize];
-        self.input.read_exact(&mut buf)?;
-        visitor.visit_str(std::str::from_utf8(&buf)?)
+        match self.input.read_slice(length as usize, &mut self.scratch)? {
+            Reference::Borrowed(bytes) => visitor.visit_borrowed_str(std::str::from_utf8(bytes)?),
+            Reference::Copied(bytes) => visitor.visit_str(std::str::from_utf8(bytes)?),
+        }
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -289,17 +300,17 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
     }
 }
 
-struct Variant<'a, R: Read> {
+struct Variant<'a, R> {
     de: &'a mut Deserializer<R>,
 }
 
-impl<'a, R: Read> Variant<'a, R> {
+impl<'a, R> Variant<'a, R> {
     fn new(de: &'a mut Deserializer<R>) -> Self {
         Variant { de }
     }
 }
 
-impl<'de, 'a, R: Read> EnumAccess<'de> for Variant<'a, R> {
+impl<'de, 'a, R: Read<'de>> EnumAccess<'de> for Variant<'a, R> {
     type Error = DeserError;
     type Variant = Self;
 
@@ -312,7 +323,7 @@ impl<'de, 'a, R: Read> EnumAccess<'de> for Variant<'a, R> {
     }
 }
 
-impl<'de, 'a, R: Read> VariantAccess<'de> for Variant<'a, R> {
+impl<'de, 'a, R: Read<'de>> VariantAccess<'de> for Variant<'a, R> {
     type Error = DeserError;
 
     fn unit_variant(self) -> Result<()> {
@@ -341,13 +352,13 @@ impl<'de, 'a, R: Read> VariantAccess<'de> for Variant<'a, R> {
     }
 }
 
-struct Counted<'a, R: Read> {
+struct Counted<'a, R> {
     de: &'a mut Deserializer<R>,
     index: usize,
     length: usize,
 }
 
-impl<'a, R: Read> Counted<'a, R> {
+impl<'a, R> Counted<'a, R> {
     fn new(de: &'a mut Deserializer<R>, length: usize) -> Self {
         Counted {
             de,
@@ -357,7 +368,7 @@ impl<'a, R: Read> Counted<'a, R> {
     }
 }
 
-impl<'de, 'a, R: Read> SeqAccess<'de> for Counted<'a, R> {
+impl<'de, 'a, R: Read<'de>> SeqAccess<'de> for Counted<'a, R> {
     type Error = DeserError;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
@@ -375,7 +386,7 @@ impl<'de, 'a, R: Read> SeqAccess<'de> for Counted<'a, R> {
     }
 }
 
-impl<'de, 'a, R: Read> MapAccess<'de> for Counted<'a, R> {
+impl<'de, 'a, R: Read<'de>> MapAccess<'de> for Counted<'a, R> {
     type Error = DeserError;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
@@ -433,6 +444,25 @@ mod test {
         assert_eq!(result, "abc");
     }
 
+    #[test]
+    fn test_deserialize_borrowed_str() {
+        let buf = vec![3, b'a', b'b', b'c'];
+        let result: &str = from_bytes(&buf).unwrap();
+        assert_eq!(result, "abc");
+        assert_eq!(result.as_ptr(), buf[1..].as_ptr());
+
+        let mut deserializer = Deserializer::new(buf.as_slice());
+        let result = <&str>::deserialize(&mut deserializer);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_deserialize_truncated_str() {
+        let buf = vec![4, b'a', b'b', b'c'];
+        let result = from_bytes::<&str>(&buf);
+        assert!(matches!(result, Err(DeserError::Io(_))));
+    }
+
     #[test]
     fn test_deserialize_struct() {
         #[derive(Deserialize, PartialEq, Eq, Debug)]
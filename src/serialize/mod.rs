@@ -2,5 +2,7 @@ pub use error::*;
 
 pub mod deserializer;
 pub mod error;
+pub mod read;
 pub mod serializer;
+mod strategy;
 mod test;
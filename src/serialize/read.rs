@@ -0,0 +1,92 @@
+use std::io;
+
+use super::error::Result;
+
+/// Bytes returned by [`Read::read_slice`]: either borrowed straight from the input
+/// (and therefore living as long as it), or copied into the deserializer's scratch buffer.
+pub enum Reference<'de, 's> {
+    Borrowed(&'de [u8]),
+    Copied(&'s [u8]),
+}
+
+/// Source of bytes for the [`Deserializer`](super::deserializer::Deserializer).
+///
+/// Inputs which are already in memory can hand out slices of themselves,
+/// which lets strings be deserialized without allocating.
+pub trait Read<'de>: io::Read {
+    fn read_slice<'s>(
+        &'s mut self,
+        len: usize,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's>>;
+}
+
+/// Input read from any [`io::Read`], e.g. a `TcpStream`.
+pub struct IoRead<R: io::Read> {
+    reader: R,
+}
+
+impl<R: io::Read> IoRead<R> {
+    pub fn new(reader: R) -> Self {
+        IoRead { reader }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: io::Read> io::Read for IoRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl<'de, R: io::Read> Read<'de> for IoRead<R> {
+    fn read_slice<'s>(
+        &'s mut self,
+        len: usize,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's>> {
+        scratch.resize(len, 0);
+        self.reader.read_exact(scratch)?;
+        Ok(Reference::Copied(scratch))
+    }
+}
+
+/// Input borrowed from a byte slice.
+pub struct SliceRead<'de> {
+    slice: &'de [u8],
+}
+
+impl<'de> SliceRead<'de> {
+    pub fn new(slice: &'de [u8]) -> Self {
+        SliceRead { slice }
+    }
+
+    /// Bytes which have not been consumed yet.
+    pub fn remaining(&self) -> &'de [u8] {
+        self.slice
+    }
+}
+
+impl<'de> io::Read for SliceRead<'de> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.slice.read(buf)
+    }
+}
+
+impl<'de> Read<'de> for SliceRead<'de> {
+    fn read_slice<'s>(
+        &'s mut self,
+        len: usize,
+        _scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's>> {
+        if self.slice.len() < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let (bytes, rest) = self.slice.split_at(len);
+        self.slice = rest;
+        Ok(Reference::Borrowed(bytes))
+    }
+}
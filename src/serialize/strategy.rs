@@ -0,0 +1,207 @@
+#![cfg(test)]
+//! Proptest strategies producing messages the protocol can carry: strings of at most
+//! 255 bytes and GUI messages which fit in a single UDP datagram.
+
+use std::collections::{BTreeMap, HashSet};
+
+use proptest::collection::{btree_map, btree_set, hash_set, vec};
+use proptest::prelude::*;
+
+use crate::{
+    Bomb, BombId, ClientMessage, Direction, DisplayMessage, Event, InputMessage, Player,
+    PlayerAddress, PlayerId, PlayerName, Position, Score, ServerMessage, ServerName,
+    MAX_STRING_LENGTH,
+};
+
+/// Any text, cut to the longest prefix which fits in `MAX_STRING_LENGTH` bytes
+/// without splitting a character.
+fn protocol_string() -> impl Strategy<Value = String> {
+    any::<String>().prop_map(|mut value| {
+        while value.len() > MAX_STRING_LENGTH {
+            value.pop();
+        }
+        value
+    })
+}
+
+fn player_name() -> impl Strategy<Value = PlayerName> {
+    protocol_string().prop_map(|name| PlayerName::new(name).unwrap())
+}
+
+fn server_name() -> impl Strategy<Value = ServerName> {
+    protocol_string().prop_map(|name| ServerName::new(name).unwrap())
+}
+
+/// Mostly real socket addresses, sometimes arbitrary text, which the protocol allows too.
+fn player_address() -> impl Strategy<Value = PlayerAddress> {
+    let address = prop_oneof![
+        3 => any::<std::net::SocketAddr>().prop_map(|address| address.to_string()),
+        1 => protocol_string(),
+    ];
+    address.prop_map(|address| PlayerAddress::new(address).unwrap())
+}
+
+fn player() -> impl Strategy<Value = Player> {
+    (player_name(), player_address()).prop_map(|(name, address)| Player { name, address })
+}
+
+fn player_id() -> impl Strategy<Value = PlayerId> {
+    any::<u8>().prop_map(PlayerId)
+}
+
+fn position() -> impl Strategy<Value = Position> {
+    any::<(u16, u16)>().prop_map(|(x, y)| Position(x, y))
+}
+
+/// At most 8 players, so that GUI messages stay well below `MAX_UDP_LENGTH`
+/// even with the longest names.
+fn players() -> impl Strategy<Value = BTreeMap<PlayerId, Player>> {
+    btree_map(player_id(), player(), 0..8)
+}
+
+fn scores() -> impl Strategy<Value = BTreeMap<PlayerId, Score>> {
+    btree_map(
+        player_id(),
+        any::<u32>().prop_map(|deaths| Score { deaths }),
+        0..8,
+    )
+}
+
+fn positions(max: usize) -> impl Strategy<Value = HashSet<Position>> {
+    hash_set(position(), 0..max)
+}
+
+pub fn direction() -> impl Strategy<Value = Direction> {
+    prop_oneof![
+        Just(Direction::Up),
+        Just(Direction::Right),
+        Just(Direction::Down),
+        Just(Direction::Left),
+    ]
+}
+
+pub fn input_message() -> impl Strategy<Value = InputMessage> {
+    prop_oneof![
+        Just(InputMessage::PlaceBomb),
+        Just(InputMessage::PlaceBlock),
+        direction().prop_map(|direction| InputMessage::Move { direction }),
+    ]
+}
+
+pub fn client_message() -> impl Strategy<Value = ClientMessage> {
+    prop_oneof![
+        player_name().prop_map(|name| ClientMessage::Join { name }),
+        Just(ClientMessage::PlaceBomb),
+        Just(ClientMessage::PlaceBlock),
+        direction().prop_map(|direction| ClientMessage::Move { direction }),
+    ]
+}
+
+pub fn event() -> impl Strategy<Value = Event> {
+    prop_oneof![
+        (any::<u32>(), position()).prop_map(|(id, position)| Event::BombPlaced {
+            id: BombId(id),
+            position,
+        }),
+        (
+            any::<u32>(),
+            vec(player_id(), 0..8),
+            btree_set(position(), 0..16)
+        )
+            .prop_map(|(id, killed, blocks_destroyed)| Event::BombExploded {
+                id: BombId(id),
+                killed,
+                blocks_destroyed,
+            }),
+        (player_id(), position()).prop_map(|(id, position)| Event::PlayerMoved { id, position }),
+        position().prop_map(|position| Event::BlockPlaced { position }),
+    ]
+}
+
+pub fn server_message() -> impl Strategy<Value = ServerMessage> {
+    prop_oneof![
+        (server_name(), any::<(u8, u16, u16, u16, u16, u16)>()).prop_map(
+            |(
+                server_name,
+                (players_count, size_x, size_y, game_length, explosion_radius, bomb_timer),
+            )| ServerMessage::Hello {
+                server_name,
+                players_count,
+                size_x,
+                size_y,
+                game_length,
+                explosion_radius,
+                bomb_timer,
+            }
+        ),
+        (player_id(), player())
+            .prop_map(|(id, player)| ServerMessage::AcceptedPlayer { id, player }),
+        players().prop_map(|players| ServerMessage::GameStarted { players }),
+        (any::<u16>(), vec(event(), 0..16))
+            .prop_map(|(turn, events)| ServerMessage::Turn { turn, events }),
+        scores().prop_map(|scores| ServerMessage::GameEnded { scores }),
+    ]
+}
+
+pub fn display_message() -> impl Strategy<Value = DisplayMessage> {
+    let lobby = (
+        server_name(),
+        any::<(u8, u16, u16, u16, u16, u16)>(),
+        players(),
+    )
+        .prop_map(
+            |(
+                server_name,
+                (players_count, size_x, size_y, game_length, explosion_radius, bomb_timer),
+                players,
+            )| DisplayMessage::Lobby {
+                server_name,
+                players_count,
+                size_x,
+                size_y,
+                game_length,
+                explosion_radius,
+                bomb_timer,
+                players,
+            },
+        );
+    let bombs = hash_set(
+        (position(), any::<u16>()).prop_map(|(position, timer)| Bomb { position, timer }),
+        0..32,
+    );
+    let game = (
+        server_name(),
+        any::<(u16, u16, u16, u16)>(),
+        players(),
+        btree_map(player_id(), position(), 0..8),
+        positions(64),
+        bombs,
+        positions(64),
+        scores(),
+    )
+        .prop_map(
+            |(
+                server_name,
+                (size_x, size_y, game_length, turn),
+                players,
+                player_positions,
+                blocks,
+                bombs,
+                explosions,
+                scores,
+            )| DisplayMessage::Game {
+                server_name,
+                size_x,
+                size_y,
+                game_length,
+                turn,
+                players,
+                player_positions,
+                blocks,
+                bombs,
+                explosions,
+                scores,
+            },
+        );
+    prop_oneof![lobby, game]
+}
@@ -1,8 +1,15 @@
 #![cfg(test)]
 
-use crate::serialize::deserializer::from_bytes;
+use proptest::prelude::*;
+use serde::de::DeserializeOwned;
+
+use crate::serialize::deserializer::{from_bytes, Deserializer};
 use crate::serialize::serializer::to_bytes;
-use crate::{BombId, Event, PlayerId, Position, ServerMessage};
+use crate::serialize::strategy;
+use crate::{
+    borrowed, BombId, ClientMessage, DisplayMessage, Event, InputMessage, Player, PlayerId,
+    Position, ServerMessage, MAX_UDP_LENGTH,
+};
 
 #[test]
 fn server_message_events() {
@@ -28,3 +35,99 @@ fn server_message_events() {
     let sm2 = from_bytes::<ServerMessage>(&to_bytes(&sm)).unwrap();
     assert_eq!(sm, sm2);
 }
+
+#[test]
+fn server_message_borrowed_player() {
+    let sm = ServerMessage::AcceptedPlayer {
+        id: PlayerId(7),
+        player: Player {
+            name: "Żółć!".parse().unwrap(),
+            address: "[fe80::1%eth0]:2022".parse().unwrap(),
+        },
+    };
+    let bytes = to_bytes(&sm);
+    let borrowed = from_bytes::<borrowed::ServerMessage>(&bytes).unwrap();
+    match &borrowed {
+        borrowed::ServerMessage::AcceptedPlayer { player, .. } => {
+            assert_eq!(player.name, "Żółć!");
+            assert_eq!(player.address, "[fe80::1%eth0]:2022");
+            assert!(bytes.as_ptr_range().contains(&player.name.as_ptr()));
+        }
+        other => panic!("unexpected message {:?}", other),
+    }
+    assert_eq!(to_bytes(&borrowed), bytes);
+    assert_eq!(ServerMessage::try_from(borrowed).unwrap(), sm);
+}
+
+/// Decodes a stream of messages until the first error, as a server reading from TCP does.
+fn decode_stream<T: DeserializeOwned>(bytes: &[u8]) {
+    let mut deserializer = Deserializer::new(bytes);
+    while deserializer.deserialize::<T>().is_ok() {}
+}
+
+proptest! {
+    #[test]
+    fn client_message_round_trip(message in strategy::client_message()) {
+        let bytes = to_bytes(&message);
+        prop_assert_eq!(from_bytes::<ClientMessage>(&bytes).unwrap(), message.clone());
+        let borrowed = from_bytes::<borrowed::ClientMessage>(&bytes).unwrap();
+        prop_assert_eq!(ClientMessage::try_from(borrowed).unwrap(), message);
+    }
+
+    #[test]
+    fn server_message_round_trip(message in strategy::server_message()) {
+        let bytes = to_bytes(&message);
+        prop_assert_eq!(from_bytes::<ServerMessage>(&bytes).unwrap(), message.clone());
+        let borrowed = from_bytes::<borrowed::ServerMessage>(&bytes).unwrap();
+        prop_assert_eq!(ServerMessage::try_from(borrowed).unwrap(), message);
+    }
+
+    #[test]
+    fn display_message_round_trip(message in strategy::display_message()) {
+        let bytes = to_bytes(&message);
+        prop_assert!(bytes.len() <= MAX_UDP_LENGTH);
+        prop_assert_eq!(from_bytes::<DisplayMessage>(&bytes).unwrap(), message.clone());
+        let borrowed = from_bytes::<borrowed::DisplayMessage>(&bytes).unwrap();
+        prop_assert_eq!(DisplayMessage::try_from(borrowed).unwrap(), message);
+    }
+
+    #[test]
+    fn input_message_round_trip(message in strategy::input_message()) {
+        prop_assert_eq!(from_bytes::<InputMessage>(&to_bytes(&message)).unwrap(), message);
+    }
+
+    #[test]
+    fn event_round_trip(event in strategy::event()) {
+        prop_assert_eq!(from_bytes::<Event>(&to_bytes(&event)).unwrap(), event);
+    }
+
+    #[test]
+    fn arbitrary_bytes_do_not_panic(bytes in proptest::collection::vec(any::<u8>(), 0..256)) {
+        let _ = from_bytes::<ClientMessage>(&bytes);
+        let _ = from_bytes::<ServerMessage>(&bytes);
+        let _ = from_bytes::<DisplayMessage>(&bytes);
+        let _ = from_bytes::<InputMessage>(&bytes);
+        let _ = from_bytes::<borrowed::ClientMessage>(&bytes);
+        let _ = from_bytes::<borrowed::ServerMessage>(&bytes);
+        let _ = from_bytes::<borrowed::DisplayMessage>(&bytes);
+        decode_stream::<ClientMessage>(&bytes);
+        decode_stream::<ServerMessage>(&bytes);
+    }
+
+    /// Random bytes rarely get past the first tag, so valid messages are damaged instead.
+    #[test]
+    fn damaged_messages_do_not_panic(
+        message in strategy::server_message(),
+        position in any::<prop::sample::Index>(),
+        byte in any::<u8>(),
+    ) {
+        let mut bytes = to_bytes(&message);
+        if !bytes.is_empty() {
+            let index = position.index(bytes.len());
+            bytes[index] = byte;
+            let _ = from_bytes::<ServerMessage>(&bytes[..]);
+            let _ = from_bytes::<borrowed::ServerMessage>(&bytes[..index]);
+            decode_stream::<ServerMessage>(&bytes[..index]);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Deterministic replay of whole matches from scripted player inputs.
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use crate::game::{Game, GameParameters};
+use crate::random::Random;
+use crate::{ClientMessage, Player, PlayerId, ServerMessage};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SimulationError {
+    #[error("Board must not be empty")]
+    EmptyBoard,
+    #[error("Expected {expected} players, got {actual}")]
+    PlayersCount { expected: u8, actual: usize },
+    #[error("Line {line}: unknown action {action:?}")]
+    UnknownAction { line: usize, action: String },
+    #[error("Line {line}: expected at most {expected} actions, got {actual}")]
+    TooManyActions {
+        line: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+/// Messages sent by the players, turn by turn. `turns[0]` holds the messages received
+/// during turn 1, since nobody can move before turn 0 is sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub turns: Vec<BTreeMap<PlayerId, ClientMessage>>,
+}
+
+impl Script {
+    /// Messages received during the given turn.
+    pub fn moves(&self, turn: u16) -> BTreeMap<PlayerId, ClientMessage> {
+        (turn as usize)
+            .checked_sub(1)
+            .and_then(|index| self.turns.get(index))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Parses a script with one line per turn, starting from turn 1.
+    ///
+    /// The n-th whitespace-separated word of a line is the action of player n:
+    /// `up`, `right`, `down`, `left`, `bomb`, `block`, or `-` for none.
+    /// Missing words mean no action, and everything after a `#` is ignored.
+    pub fn parse(input: &str, players_count: u8) -> Result<Script, SimulationError> {
+        let turns = input
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                let line_number = index + 1;
+                let line = line.split('#').next().unwrap_or_default();
+                let actions = line.split_whitespace().collect::<Vec<_>>();
+                if actions.len() > players_count as usize {
+                    return Err(SimulationError::TooManyActions {
+                        line: line_number,
+                        expected: players_count as usize,
+                        actual: actions.len(),
+                    });
+                }
+                let mut moves = BTreeMap::new();
+                for (id, action) in actions.into_iter().enumerate() {
+                    let message = match action {
+                        "-" => continue,
+                        "bomb" => ClientMessage::PlaceBomb,
+                        "block" => ClientMessage::PlaceBlock,
+                        _ => ClientMessage::Move {
+                            direction: action.parse().map_err(|_| {
+                                SimulationError::UnknownAction {
+                                    line: line_number,
+                                    action: action.to_string(),
+                                }
+                            })?,
+                        },
+                    };
+                    moves.insert(PlayerId(id as u8), message);
+                }
+                Ok(moves)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Script { turns })
+    }
+}
+
+/// Plays a single game, from `AcceptedPlayer` to `GameEnded`, for players joining
+/// in the given order. The generator is not reset, so consecutive games can share it.
+pub fn simulate_game(
+    parameters: &GameParameters,
+    players: &[Player],
+    script: &Script,
+    random: &mut Random,
+) -> Result<Vec<ServerMessage>, SimulationError> {
+    let board = parameters.board().ok_or(SimulationError::EmptyBoard)?;
+    if players.len() != parameters.players_count as usize {
+        return Err(SimulationError::PlayersCount {
+            expected: parameters.players_count,
+            actual: players.len(),
+        });
+    }
+
+    let players = players
+        .iter()
+        .enumerate()
+        .map(|(id, player)| (PlayerId(id as u8), player.clone()))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut messages = players
+        .iter()
+        .map(|(&id, player)| ServerMessage::AcceptedPlayer {
+            id,
+            player: player.clone(),
+        })
+        .collect::<Vec<_>>();
+    messages.push(ServerMessage::GameStarted {
+        players: players.clone(),
+    });
+
+    let (mut game, turn) = Game::start(parameters, board, players, random);
+    messages.push(turn);
+    while !game.is_finished() {
+        let moves = script.moves(game.turn() + 1);
+        messages.push(game.play_turn(&moves, random));
+    }
+    messages.push(game.game_ended());
+
+    Ok(messages)
+}
+
+/// Everything a client connected from the very beginning receives during one game.
+pub fn simulate(
+    parameters: &GameParameters,
+    seed: u32,
+    players: &[Player],
+    script: &Script,
+) -> Result<Vec<ServerMessage>, SimulationError> {
+    let mut random = Random::new(seed);
+    let mut messages = vec![parameters.hello()];
+    messages.extend(simulate_game(parameters, players, script, &mut random)?);
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Direction, Event, Position, Score};
+
+    fn parameters() -> GameParameters {
+        GameParameters {
+            server_name: "test".parse().unwrap(),
+            players_count: 2,
+            size_x: 5,
+            size_y: 4,
+            game_length: 3,
+            explosion_radius: 1,
+            bomb_timer: 1,
+            initial_blocks: 2,
+        }
+    }
+
+    fn players(count: u8) -> Vec<Player> {
+        (0..count)
+            .map(|id| Player {
+                name: format!("player{}", id).parse().unwrap(),
+                address: format!("127.0.0.1:{}", 10000 + id as u16).parse().unwrap(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_script() {
+        let script = Script::parse("up bomb\n# comment\n- block # trailing\n\nleft", 2).unwrap();
+        assert_eq!(script.turns.len(), 5);
+        assert_eq!(
+            script.moves(1),
+            BTreeMap::from([
+                (
+                    PlayerId(0),
+                    ClientMessage::Move {
+                        direction: Direction::Up
+                    }
+                ),
+                (PlayerId(1), ClientMessage::PlaceBomb),
+            ])
+        );
+        assert!(script.moves(2).is_empty());
+        assert_eq!(
+            script.moves(3),
+            BTreeMap::from([(PlayerId(1), ClientMessage::PlaceBlock)])
+        );
+        assert!(script.moves(0).is_empty());
+        assert!(script.moves(6).is_empty());
+    }
+
+    #[test]
+    fn parse_script_errors() {
+        assert_eq!(
+            Script::parse("up\njump", 1),
+            Err(SimulationError::UnknownAction {
+                line: 2,
+                action: "jump".to_string()
+            })
+        );
+        assert_eq!(
+            Script::parse("up up up", 2),
+            Err(SimulationError::TooManyActions {
+                line: 1,
+                expected: 2,
+                actual: 3
+            })
+        );
+    }
+
+    #[test]
+    fn initial_turn_follows_generator() {
+        let messages = simulate(&parameters(), 1234, &players(2), &Script::default()).unwrap();
+        assert_eq!(messages[0], parameters().hello());
+        assert!(matches!(
+            messages[1],
+            ServerMessage::AcceptedPlayer {
+                id: PlayerId(0),
+                ..
+            }
+        ));
+        assert!(matches!(messages[3], ServerMessage::GameStarted { .. }));
+
+        let mut random = Random::new(1234);
+        let mut draw = |size: u32| (random.next_u32() % size) as u16;
+        let mut expected = Vec::new();
+        for id in 0..2 {
+            let position = Position(draw(5), draw(4));
+            expected.push(Event::PlayerMoved {
+                id: PlayerId(id),
+                position,
+            });
+        }
+        let mut blocks = Vec::new();
+        for _ in 0..2 {
+            let position = Position(draw(5), draw(4));
+            if !blocks.contains(&position) {
+                blocks.push(position);
+                expected.push(Event::BlockPlaced { position });
+            }
+        }
+        assert_eq!(
+            messages[4],
+            ServerMessage::Turn {
+                turn: 0,
+                events: expected
+            }
+        );
+    }
+
+    #[test]
+    fn whole_game() {
+        let parameters = GameParameters {
+            players_count: 1,
+            initial_blocks: 0,
+            ..parameters()
+        };
+        let script = Script::parse("bomb\n-\nup", 1).unwrap();
+        let messages = simulate(&parameters, 1234, &players(1), &script).unwrap();
+        // Hello, AcceptedPlayer, GameStarted, turns 0 to 3 and GameEnded.
+        assert_eq!(messages.len(), 8);
+
+        let start = match &messages[3] {
+            ServerMessage::Turn { turn: 0, events } => match events[..] {
+                [Event::PlayerMoved { position, .. }] => position,
+                _ => panic!("unexpected events {:?}", events),
+            },
+            other => panic!("unexpected message {:?}", other),
+        };
+        assert_eq!(
+            messages[4],
+            ServerMessage::Turn {
+                turn: 1,
+                events: vec![Event::BombPlaced {
+                    id: crate::BombId(0),
+                    position: start
+                }]
+            }
+        );
+        match &messages[5] {
+            ServerMessage::Turn { turn: 2, events } => {
+                assert!(matches!(
+                    &events[..],
+                    [
+                        Event::BombExploded { killed, blocks_destroyed, .. },
+                        Event::PlayerMoved { id: PlayerId(0), .. },
+                    ] if killed == &[PlayerId(0)] && blocks_destroyed.is_empty()
+                ));
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+        assert!(matches!(messages[6], ServerMessage::Turn { turn: 3, .. }));
+        assert_eq!(
+            messages[7],
+            ServerMessage::GameEnded {
+                scores: BTreeMap::from([(PlayerId(0), Score { deaths: 1 })])
+            }
+        );
+    }
+
+    #[test]
+    fn simulation_is_deterministic() {
+        let script = Script::parse("bomb bomb\nleft right\nblock up", 2).unwrap();
+        let first = simulate(&parameters(), 77, &players(2), &script).unwrap();
+        let second = simulate(&parameters(), 77, &players(2), &script).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn wrong_players_count() {
+        assert_eq!(
+            simulate(&parameters(), 1, &players(1), &Script::default()),
+            Err(SimulationError::PlayersCount {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+}
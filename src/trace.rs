@@ -0,0 +1,720 @@
+//! Recorded sessions and checking them against the game rules.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Game, GameParameters};
+use crate::random::Random;
+use crate::simulation::Script;
+use crate::{ClientMessage, Event, PlayerId, Position, Score, ServerMessage};
+
+/// A single entry of a recorded session, in the order in which it was observed.
+/// Messages sent by a player between `Turn n - 1` and `Turn n` are the moves for turn `n`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceRecord {
+    Server(ServerMessage),
+    Client {
+        id: PlayerId,
+        message: ClientMessage,
+    },
+}
+
+/// Interleaves the messages of a simulated session with the moves of its script.
+pub fn to_trace(messages: Vec<ServerMessage>, script: &Script) -> Vec<TraceRecord> {
+    let mut records = Vec::new();
+    for message in messages {
+        if let ServerMessage::Turn { turn, .. } = message {
+            for (id, message) in script.moves(turn) {
+                records.push(TraceRecord::Client { id, message });
+            }
+        }
+        records.push(TraceRecord::Server(message));
+    }
+    records
+}
+
+/// What went wrong in the first message which does not follow the rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The server did not send an event it should have sent.
+    MissingEvent {
+        expected: Event,
+    },
+    /// The server sent an event it should not have sent.
+    UnexpectedEvent {
+        actual: Event,
+    },
+    /// The server sent a different event than expected in its place.
+    WrongEvent {
+        expected: Event,
+        actual: Event,
+    },
+    /// The server sent the right events, but not in the order of README 2.8.
+    WrongOrder {
+        expected: Vec<Event>,
+        actual: Vec<Event>,
+    },
+    WrongTurnNumber {
+        expected: u16,
+        actual: u16,
+    },
+    WrongScores {
+        expected: BTreeMap<PlayerId, Score>,
+        actual: BTreeMap<PlayerId, Score>,
+    },
+    UnexpectedMessage {
+        message: ServerMessage,
+        reason: String,
+    },
+    /// The trace ended in the middle of a game.
+    Truncated,
+}
+
+/// The first place where a trace departs from the rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the offending record in the trace.
+    pub record: usize,
+    /// Turn of the game in progress, if there is one.
+    pub turn: Option<u16>,
+    /// Players killed in this turn according to the rules, which helps to tell
+    /// wrong respawns apart from wrong moves.
+    pub killed: BTreeSet<PlayerId>,
+    pub mismatch: Mismatch,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub games: usize,
+    pub turns: usize,
+}
+
+/// Replays the trace with the given seed and returns the first divergence from the rules.
+///
+/// `initial_blocks` is needed because, unlike the other parameters, `Hello` does not carry it.
+pub fn check_trace(
+    records: &[TraceRecord],
+    seed: u32,
+    initial_blocks: u16,
+) -> Result<Summary, Box<Divergence>> {
+    let mut random = Random::new(seed);
+    let mut parameters = None;
+    let mut game: Option<Game> = None;
+    let mut moves = BTreeMap::new();
+    let mut summary = Summary::default();
+
+    for (index, record) in records.iter().enumerate() {
+        let diverge = |game: &Option<Game>, killed: BTreeSet<PlayerId>, mismatch| {
+            Box::new(Divergence {
+                record: index,
+                turn: game.as_ref().map(Game::turn),
+                killed,
+                mismatch,
+            })
+        };
+        let unexpected = |game: &Option<Game>, message: &ServerMessage, reason: &str| {
+            diverge(
+                game,
+                BTreeSet::new(),
+                Mismatch::UnexpectedMessage {
+                    message: message.clone(),
+                    reason: reason.to_string(),
+                },
+            )
+        };
+
+        let message = match record {
+            TraceRecord::Client { id, message } => {
+                if game.is_some() {
+                    moves.insert(*id, message.clone());
+                }
+                continue;
+            }
+            TraceRecord::Server(message) => message,
+        };
+
+        match message {
+            ServerMessage::Hello {
+                server_name,
+                players_count,
+                size_x,
+                size_y,
+                game_length,
+                explosion_radius,
+                bomb_timer,
+            } => {
+                if parameters.is_some() {
+                    return Err(unexpected(&game, message, "Hello was already received"));
+                }
+                parameters = Some(GameParameters {
+                    server_name: server_name.clone(),
+                    players_count: *players_count,
+                    size_x: *size_x,
+                    size_y: *size_y,
+                    game_length: *game_length,
+                    explosion_radius: *explosion_radius,
+                    bomb_timer: *bomb_timer,
+                    initial_blocks,
+                });
+            }
+            ServerMessage::AcceptedPlayer { .. } => {
+                if game.is_some() {
+                    return Err(unexpected(&game, message, "a game is in progress"));
+                }
+            }
+            ServerMessage::GameStarted { players } => {
+                let parameters = match &parameters {
+                    Some(parameters) => parameters,
+                    None => return Err(unexpected(&game, message, "Hello was not received")),
+                };
+                if game.is_some() {
+                    return Err(unexpected(&game, message, "a game is in progress"));
+                }
+                if players.len() != parameters.players_count as usize {
+                    return Err(unexpected(&game, message, "wrong number of players"));
+                }
+                let board = match parameters.board() {
+                    Some(board) => board,
+                    None => return Err(unexpected(&game, message, "the board is empty")),
+                };
+                let (started, turn) = Game::start(parameters, board, players.clone(), &mut random);
+                game = Some(started);
+                moves.clear();
+                // Turn 0 has to be the very next server message.
+                match next_server_message(records, index) {
+                    Some((turn_index, actual)) => {
+                        if let Err(mismatch) = compare_turns(&turn, actual) {
+                            return Err(Box::new(Divergence {
+                                record: turn_index,
+                                turn: Some(0),
+                                killed: BTreeSet::new(),
+                                mismatch,
+                            }));
+                        }
+                    }
+                    None => return Err(diverge(&game, BTreeSet::new(), Mismatch::Truncated)),
+                }
+                summary.games += 1;
+            }
+            ServerMessage::Turn { turn: 0, .. } => {
+                // Already compared when the game started.
+                if !matches!(&game, Some(game) if game.turn() == 0) {
+                    return Err(unexpected(&game, message, "Turn 0 must follow GameStarted"));
+                }
+            }
+            ServerMessage::Turn { .. } => {
+                let current = match &mut game {
+                    Some(current) if !current.is_finished() => current,
+                    _ => return Err(unexpected(&game, message, "no game is in progress")),
+                };
+                let expected = current.play_turn(&moves, &mut random);
+                moves.clear();
+                if let Err(mismatch) = compare_turns(&expected, message) {
+                    return Err(diverge(&game, killed_in(&expected), mismatch));
+                }
+                summary.turns += 1;
+            }
+            ServerMessage::GameEnded { scores } => {
+                let current = match &game {
+                    Some(current) if current.is_finished() => current,
+                    Some(_) => return Err(unexpected(&game, message, "the game is not over")),
+                    None => return Err(unexpected(&game, message, "no game is in progress")),
+                };
+                if current.scores() != scores {
+                    return Err(diverge(
+                        &game,
+                        BTreeSet::new(),
+                        Mismatch::WrongScores {
+                            expected: current.scores().clone(),
+                            actual: scores.clone(),
+                        },
+                    ));
+                }
+                game = None;
+            }
+        }
+    }
+
+    match game {
+        Some(game) => Err(Box::new(Divergence {
+            record: records.len(),
+            turn: Some(game.turn()),
+            killed: BTreeSet::new(),
+            mismatch: Mismatch::Truncated,
+        })),
+        None => Ok(summary),
+    }
+}
+
+fn next_server_message(records: &[TraceRecord], index: usize) -> Option<(usize, &ServerMessage)> {
+    records
+        .iter()
+        .enumerate()
+        .skip(index + 1)
+        .find_map(|(index, record)| match record {
+            TraceRecord::Server(message) => Some((index, message)),
+            TraceRecord::Client { .. } => None,
+        })
+}
+
+fn killed_in(turn: &ServerMessage) -> BTreeSet<PlayerId> {
+    match turn {
+        ServerMessage::Turn { events, .. } => events
+            .iter()
+            .flat_map(|event| match event {
+                Event::BombExploded { killed, .. } => killed.clone(),
+                _ => Vec::new(),
+            })
+            .collect(),
+        _ => BTreeSet::new(),
+    }
+}
+
+/// Rewrites the events of a turn so that differences allowed by README 2.8 disappear:
+/// `killed` is compared as a set, so its robots are sorted and duplicates are ignored,
+/// and the explosions are sorted too, since bombs are not processed in any particular order.
+/// Explosions still have to precede the moves of the players.
+pub fn normalize_events(events: &[Event]) -> Vec<Event> {
+    let mut events = events
+        .iter()
+        .map(|event| match event {
+            Event::BombExploded {
+                id,
+                killed,
+                blocks_destroyed,
+            } => {
+                let mut killed = killed.clone();
+                killed.sort();
+                killed.dedup();
+                Event::BombExploded {
+                    id: *id,
+                    killed,
+                    blocks_destroyed: blocks_destroyed.clone(),
+                }
+            }
+            other => other.clone(),
+        })
+        .collect::<Vec<_>>();
+    let explosions = events
+        .iter()
+        .take_while(|event| matches!(event, Event::BombExploded { .. }))
+        .count();
+    events[..explosions].sort_by_key(|event| match event {
+        Event::BombExploded { id, .. } => *id,
+        _ => unreachable!("only explosions are sorted"),
+    });
+    events
+}
+
+/// [`normalize_events`] applied to a whole message.
+pub fn normalize(message: &ServerMessage) -> ServerMessage {
+    match message {
+        ServerMessage::Turn { turn, events } => ServerMessage::Turn {
+            turn: *turn,
+            events: normalize_events(events),
+        },
+        other => other.clone(),
+    }
+}
+
+/// Whether two events are about the same thing, so that one can be reported
+/// as a wrong version of the other.
+fn same_subject(a: &Event, b: &Event) -> bool {
+    match (a, b) {
+        (Event::BombPlaced { id: a, .. }, Event::BombPlaced { id: b, .. }) => a == b,
+        (Event::BombExploded { id: a, .. }, Event::BombExploded { id: b, .. }) => a == b,
+        (Event::PlayerMoved { id: a, .. }, Event::PlayerMoved { id: b, .. }) => a == b,
+        (Event::BlockPlaced { .. }, Event::BlockPlaced { .. }) => true,
+        _ => false,
+    }
+}
+
+fn is_permutation(a: &[Event], b: &[Event]) -> bool {
+    let mut rest = b.to_vec();
+    a.len() == b.len()
+        && a.iter()
+            .all(|event| match rest.iter().position(|other| other == event) {
+                Some(index) => {
+                    rest.swap_remove(index);
+                    true
+                }
+                None => false,
+            })
+}
+
+fn compare_turns(expected: &ServerMessage, actual: &ServerMessage) -> Result<(), Mismatch> {
+    let (expected_turn, expected) = match expected {
+        ServerMessage::Turn { turn, events } => (*turn, events),
+        _ => unreachable!("the rules only produce turns here"),
+    };
+    let (actual_turn, actual) = match actual {
+        ServerMessage::Turn { turn, events } => (*turn, events),
+        other => {
+            return Err(Mismatch::UnexpectedMessage {
+                message: other.clone(),
+                reason: format!("expected Turn {}", expected_turn),
+            })
+        }
+    };
+    if expected_turn != actual_turn {
+        return Err(Mismatch::WrongTurnNumber {
+            expected: expected_turn,
+            actual: actual_turn,
+        });
+    }
+
+    let expected = normalize_events(expected);
+    let actual = normalize_events(actual);
+    if expected == actual {
+        return Ok(());
+    }
+
+    if is_permutation(&expected, &actual) {
+        return Err(Mismatch::WrongOrder { expected, actual });
+    }
+
+    let mut expected_iter = expected.iter().peekable();
+    let mut actual_iter = actual.iter().peekable();
+    loop {
+        match (expected_iter.peek(), actual_iter.peek()) {
+            (Some(e), Some(a)) if e == a => {
+                expected_iter.next();
+                actual_iter.next();
+            }
+            (Some(&e), Some(&a)) => {
+                return Err(if same_subject(e, a) {
+                    Mismatch::WrongEvent {
+                        expected: e.clone(),
+                        actual: a.clone(),
+                    }
+                } else if !expected.contains(a) {
+                    Mismatch::UnexpectedEvent { actual: a.clone() }
+                } else {
+                    Mismatch::MissingEvent {
+                        expected: e.clone(),
+                    }
+                });
+            }
+            (Some(&e), None) => {
+                return Err(Mismatch::MissingEvent {
+                    expected: e.clone(),
+                })
+            }
+            (None, Some(&a)) => return Err(Mismatch::UnexpectedEvent { actual: a.clone() }),
+            (None, None) => unreachable!("the turns differ"),
+        }
+    }
+}
+
+fn position(Position(x, y): &Position) -> String {
+    format!("({}, {})", x, y)
+}
+
+fn positions<'a>(positions: impl IntoIterator<Item = &'a Position>) -> String {
+    positions
+        .into_iter()
+        .map(position)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Divergence {
+    fn describe_wrong(&self, expected: &Event, actual: &Event) -> String {
+        match (expected, actual) {
+            (
+                Event::PlayerMoved {
+                    id,
+                    position: expected,
+                },
+                Event::PlayerMoved {
+                    position: actual, ..
+                },
+            ) if self.turn == Some(0) => format!(
+                "robot {} placed at {} instead of {} (check the use of the random generator)",
+                id.0,
+                position(actual),
+                position(expected)
+            ),
+            (
+                Event::PlayerMoved {
+                    id,
+                    position: expected,
+                },
+                Event::PlayerMoved {
+                    position: actual, ..
+                },
+            ) if self.killed.contains(id) => format!(
+                "wrong respawn position of robot {}: {} instead of {} (check the use of the random generator)",
+                id.0,
+                position(actual),
+                position(expected)
+            ),
+            (
+                Event::PlayerMoved {
+                    id,
+                    position: expected,
+                },
+                Event::PlayerMoved {
+                    position: actual, ..
+                },
+            ) => format!(
+                "robot {} moved to {} instead of {}",
+                id.0,
+                position(actual),
+                position(expected)
+            ),
+            (
+                Event::BombExploded {
+                    id,
+                    killed: expected_killed,
+                    blocks_destroyed: expected_blocks,
+                },
+                Event::BombExploded {
+                    killed: actual_killed,
+                    blocks_destroyed: actual_blocks,
+                    ..
+                },
+            ) => {
+                let mut problems = Vec::new();
+                let missed = expected_blocks.difference(actual_blocks).collect::<Vec<_>>();
+                if !missed.is_empty() {
+                    problems.push(format!("missed blocks {}", positions(missed)));
+                }
+                let extra = actual_blocks.difference(expected_blocks).collect::<Vec<_>>();
+                if !extra.is_empty() {
+                    problems.push(format!("destroyed extra blocks {}", positions(extra)));
+                }
+                if expected_killed != actual_killed {
+                    problems.push(format!(
+                        "killed {:?} instead of {:?}",
+                        actual_killed.iter().map(|id| id.0).collect::<Vec<_>>(),
+                        expected_killed.iter().map(|id| id.0).collect::<Vec<_>>()
+                    ));
+                }
+                format!("explosion of bomb {}: {}", id.0, problems.join(", "))
+            }
+            (
+                Event::BombPlaced {
+                    id,
+                    position: expected,
+                },
+                Event::BombPlaced {
+                    position: actual, ..
+                },
+            ) => format!(
+                "bomb {} placed at {} instead of {}",
+                id.0,
+                position(actual),
+                position(expected)
+            ),
+            _ => format!("got {:?} instead of {:?}", actual, expected),
+        }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.turn {
+            Some(turn) => write!(f, "Turn {} (record {}): ", turn, self.record)?,
+            None => write!(f, "Record {}: ", self.record)?,
+        }
+        match &self.mismatch {
+            Mismatch::MissingEvent { expected } => match expected {
+                Event::PlayerMoved { id, position: p } if self.killed.contains(id) => {
+                    write!(f, "robot {} was not respawned at {}", id.0, position(p))
+                }
+                Event::PlayerMoved { id, position: p } => {
+                    write!(
+                        f,
+                        "legal move of robot {} to {} was rejected",
+                        id.0,
+                        position(p)
+                    )
+                }
+                Event::BombExploded { id, .. } => write!(f, "bomb {} did not explode", id.0),
+                Event::BombPlaced { id, position: p } => {
+                    write!(f, "bomb {} was not placed at {}", id.0, position(p))
+                }
+                Event::BlockPlaced { position: p } => {
+                    write!(f, "block was not placed at {}", position(p))
+                }
+            },
+            Mismatch::UnexpectedEvent { actual } => match actual {
+                Event::PlayerMoved { id, position: p } => {
+                    write!(
+                        f,
+                        "illegal move accepted: robot {} moved to {}",
+                        id.0,
+                        position(p)
+                    )
+                }
+                Event::BombExploded { id, .. } => {
+                    write!(f, "bomb {} exploded at the wrong time", id.0)
+                }
+                Event::BombPlaced { id, position: p } => {
+                    write!(f, "unexpected bomb {} placed at {}", id.0, position(p))
+                }
+                Event::BlockPlaced { position: p } => {
+                    write!(f, "illegal block placement accepted at {}", position(p))
+                }
+            },
+            Mismatch::WrongEvent { expected, actual } => {
+                write!(f, "{}", self.describe_wrong(expected, actual))
+            }
+            Mismatch::WrongOrder { expected, actual } => write!(
+                f,
+                "events are in the wrong order: got {:?}, expected {:?}",
+                actual, expected
+            ),
+            Mismatch::WrongTurnNumber { expected, actual } => {
+                write!(f, "got Turn {} instead of Turn {}", actual, expected)
+            }
+            Mismatch::WrongScores { expected, actual } => {
+                write!(f, "wrong scores: got {:?}, expected {:?}", actual, expected)
+            }
+            Mismatch::UnexpectedMessage { message, reason } => {
+                write!(f, "unexpected {:?}: {}", message, reason)
+            }
+            Mismatch::Truncated => write!(f, "the trace ends in the middle of a game"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::simulation::simulate;
+    use crate::Player;
+
+    const SEED: u32 = 1234;
+
+    fn parameters() -> GameParameters {
+        GameParameters {
+            server_name: "test".parse().unwrap(),
+            players_count: 1,
+            size_x: 5,
+            size_y: 4,
+            game_length: 3,
+            explosion_radius: 1,
+            bomb_timer: 1,
+            initial_blocks: 0,
+        }
+    }
+
+    /// The robot puts a block under itself, a bomb on top of it, and the bomb
+    /// destroys both the block and the robot in turn 3.
+    fn trace() -> Vec<TraceRecord> {
+        let players = [Player {
+            name: "player0".parse().unwrap(),
+            address: "127.0.0.1:10000".parse().unwrap(),
+        }];
+        let script = Script::parse("block\nbomb\n-", 1).unwrap();
+        let messages = simulate(&parameters(), SEED, &players, &script).unwrap();
+        to_trace(messages, &script)
+    }
+
+    fn events_of(records: &mut [TraceRecord], turn: u16) -> (usize, &mut Vec<Event>) {
+        records
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, record)| match record {
+                TraceRecord::Server(ServerMessage::Turn { turn: t, events }) if *t == turn => {
+                    Some((index, events))
+                }
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn simulated_trace_follows_the_rules() {
+        let records = trace();
+        assert!(records.contains(&TraceRecord::Client {
+            id: PlayerId(0),
+            message: ClientMessage::PlaceBlock
+        }));
+        assert_eq!(
+            check_trace(&records, SEED, 0),
+            Ok(Summary { games: 1, turns: 3 })
+        );
+    }
+
+    #[test]
+    fn wrong_seed_is_noticed_at_turn_0() {
+        let divergence = check_trace(&trace(), SEED + 1, 0).unwrap_err();
+        assert_eq!(divergence.turn, Some(0));
+    }
+
+    #[test]
+    fn missed_block() {
+        let mut records = trace();
+        let (index, events) = events_of(&mut records, 3);
+        match &mut events[0] {
+            Event::BombExploded {
+                blocks_destroyed, ..
+            } => blocks_destroyed.clear(),
+            other => panic!("unexpected event {:?}", other),
+        }
+        let divergence = check_trace(&records, SEED, 0).unwrap_err();
+        assert_eq!(divergence.record, index);
+        assert_eq!(divergence.turn, Some(3));
+        assert!(matches!(divergence.mismatch, Mismatch::WrongEvent { .. }));
+        assert!(divergence.to_string().contains("missed blocks"));
+    }
+
+    #[test]
+    fn wrong_respawn() {
+        let mut records = trace();
+        let (_, events) = events_of(&mut records, 3);
+        match &mut events[1] {
+            Event::PlayerMoved { position, .. } => position.0 = (position.0 + 1) % 5,
+            other => panic!("unexpected event {:?}", other),
+        }
+        let divergence = check_trace(&records, SEED, 0).unwrap_err();
+        assert_eq!(divergence.killed, BTreeSet::from([PlayerId(0)]));
+        assert!(divergence.to_string().contains("wrong respawn position"));
+    }
+
+    #[test]
+    fn duplicate_kill_ignored() {
+        let mut records = trace();
+        let (_, events) = events_of(&mut records, 3);
+        match &mut events[0] {
+            Event::BombExploded { killed, .. } => killed.push(killed[0]),
+            other => panic!("unexpected event {:?}", other),
+        }
+        check_trace(&records, SEED, 0).unwrap();
+    }
+
+    #[test]
+    fn illegal_move_accepted() {
+        let mut records = trace();
+        let (_, events) = events_of(&mut records, 2);
+        events.push(Event::PlayerMoved {
+            id: PlayerId(0),
+            position: Position(0, 0),
+        });
+        let divergence = check_trace(&records, SEED, 0).unwrap_err();
+        assert_eq!(divergence.turn, Some(2));
+        assert!(divergence.to_string().contains("illegal move accepted"));
+    }
+
+    #[test]
+    fn wrong_order() {
+        let mut records = trace();
+        let (_, events) = events_of(&mut records, 3);
+        events.reverse();
+        let divergence = check_trace(&records, SEED, 0).unwrap_err();
+        assert!(matches!(divergence.mismatch, Mismatch::WrongOrder { .. }));
+    }
+
+    #[test]
+    fn truncated() {
+        let mut records = trace();
+        records.pop();
+        let divergence = check_trace(&records, SEED, 0).unwrap_err();
+        assert_eq!(divergence.mismatch, Mismatch::Truncated);
+    }
+}